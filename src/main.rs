@@ -5,10 +5,19 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
 
 use parsing::lexer::{Token, tokenize};
 use parsing::parser::{DataType, Definition, parse};
-use runtime::runtime::run;
+use parsing::resolver::resolve;
+use runtime::runtime::{Flow, max_depth, run};
+use std_lib::std_lib::{MODULE, enter, tie};
+
+const STACK_PER_CALL: usize = 256 * 1024;
+// Even a shallow limit leaves the interpreter as much stack as a main thread gets
+const STACK_FLOOR: usize = 8 * 1024 * 1024;
 
 fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -22,6 +31,32 @@ fn main() -> std::io::Result<()> {
     let mut script = String::new();
     file.read_to_string(&mut script)?;
 
+    let depth = match max_depth() {
+        Ok(depth) => depth,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    // Each nested rash call takes up a good chunk of the native stack, so the interpreter gets one big enough for MAX_DEPTH of them
+    let path = PathBuf::from(&args[1]);
+    let stack = depth.saturating_mul(STACK_PER_CALL).max(STACK_FLOOR);
+    let interpreter = match thread::Builder::new().stack_size(stack).spawn(move || interpret(path, script)) {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            eprintln!("RECURSION ERROR: Couldn't get a {} MiB stack for RASH_MAX_DEPTH={}, try a lower limit ({})", stack / (1024 * 1024), depth, error);
+            std::process::exit(1);
+        }
+    };
+    if interpreter.join().is_err() {
+        std::process::exit(101);
+    }
+
+    return Ok(());
+}
+
+fn interpret(path: PathBuf, script: String) {
     let mut tokens: Vec<Token> = Vec::new();
 
     for line in script.lines() {
@@ -32,13 +67,35 @@ fn main() -> std::io::Result<()> {
     }
 
     // println!("{:?}", tokens);
-    let tree = parse(tokens);
+    let mut tree = parse(tokens);
     // println!("Tree: {:?} Length: {:?}", tree, tree.len());
 
-    let mut scopes: Vec<HashMap<String, DataType>> = Vec::new();
-    scopes.push(HashMap::new());
-    let mut functions: HashMap<String, Definition> = HashMap::new();
-    run(&tree, &mut scopes, &mut functions);
-
-    return Ok(());
+    let mut functions: HashMap<String, Rc<Definition>> = HashMap::new();
+    let scope = enter(&path);
+    tie(&mut tree, &scope);
+    // The script's globals are marked like a module's, so its functions get them back when called from one
+    let mut globals: HashMap<String, DataType> = HashMap::new();
+    globals.insert(MODULE.to_string(), DataType::string(scope.path.clone()));
+    let mut scopes: Vec<HashMap<String, DataType>> = vec![globals];
+    let errors = resolve(&mut tree, &mut functions);
+    if !errors.is_empty() {
+        for error in errors {
+            eprintln!("{}", error);
+        }
+        std::process::exit(1);
+    }
+    match run(&tree, &mut scopes, &mut functions) {
+        Flow::Error(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        Flow::Exit(code) => {
+            std::process::exit(code);
+        }
+        Flow::Break(..) | Flow::Continue(..) => {
+            eprintln!("LOOP ERROR: break and continue can only be used inside of a loop");
+            std::process::exit(1);
+        }
+        _ => {}
+    }
 }
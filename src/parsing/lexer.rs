@@ -1,7 +1,7 @@
 use regex::Regex;
 use once_cell::sync::Lazy;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenType {
     Tilda,
     Print,
@@ -14,6 +14,7 @@ pub enum TokenType {
     Minus,
     Times,
     Divide,
+    FloorDivide,
     For,
     Semicolon,
     OpeningBracket,
@@ -46,6 +47,11 @@ pub enum TokenType {
     Function,
     Import,
     Return,
+    Let,
+    Global,
+    As,
+    From,
+    Export,
     OpeningSquareBracket,
     ClosingSquareBracket,
     Length,
@@ -55,6 +61,34 @@ pub enum TokenType {
     Delete,
     String,
     Int,
+    Colon,
+    In,
+    DotDot,
+    Ellipsis,
+    PlusEquals,
+    MinusEquals,
+    TimesEquals,
+    DivideEquals,
+    While,
+    Loop,
+    Try,
+    Catch,
+    Break,
+    Continue,
+    Match,
+    FatArrow,
+    Pipe,
+    Modulo,
+    ModuloEquals,
+    Power,
+    BitAnd,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Question,
+    Nil,
+    Coalesce,
+    OptionalIndex,
 }
 
 #[derive(Debug, Clone)]
@@ -63,7 +97,7 @@ pub struct Token<> {
     pub value: String,
 }
 
-static KEYWORDS: Lazy<[(TokenType, Regex); 51]> = Lazy::new(|| {
+static KEYWORDS: Lazy<[(TokenType, Regex); 85]> = Lazy::new(|| {
     [
         (TokenType::Comment, Regex::new(r"^[/][/][ ]*").unwrap()),
         (TokenType::Newline, Regex::new(r"^[\n][ ]*").unwrap()),
@@ -77,31 +111,58 @@ static KEYWORDS: Lazy<[(TokenType, Regex); 51]> = Lazy::new(|| {
         (TokenType::String, Regex::new(r"^string[ ]*").unwrap()),
         (TokenType::Import, Regex::new(r"^import[ ]*").unwrap()),
         (TokenType::Return, Regex::new(r"^return[ ]*").unwrap()),
+        (TokenType::Let, Regex::new(r"^let\b[ ]*").unwrap()),
+        (TokenType::Global, Regex::new(r"^global\b[ ]*").unwrap()),
+        (TokenType::As, Regex::new(r"^as\b[ ]*").unwrap()),
+        (TokenType::From, Regex::new(r"^from\b[ ]*").unwrap()),
+        (TokenType::Export, Regex::new(r"^export\b[ ]*").unwrap()),
         (TokenType::Comma, Regex::new(r"^[,][ ]*").unwrap()),
+        (TokenType::FloorDivide, Regex::new(r"^~/[ ]*").unwrap()),
         (TokenType::Tilda, Regex::new(r"^~[ ]*").unwrap()),
         (TokenType::For, Regex::new(r"^for[ ]*").unwrap()),
+        (TokenType::In, Regex::new(r"^in\b[ ]*").unwrap()),
+        (TokenType::While, Regex::new(r"^while\b[ ]*").unwrap()),
+        (TokenType::Loop, Regex::new(r"^loop\b[ ]*").unwrap()),
+        (TokenType::Try, Regex::new(r"^try\b[ ]*").unwrap()),
+        (TokenType::Catch, Regex::new(r"^catch\b[ ]*").unwrap()),
+        (TokenType::Break, Regex::new(r"^break\b[ ]*").unwrap()),
+        (TokenType::Continue, Regex::new(r"^continue\b[ ]*").unwrap()),
+        (TokenType::Match, Regex::new(r"^match\b[ ]*").unwrap()),
         (TokenType::If, Regex::new(r"^if[ ]*").unwrap()),
         (TokenType::ElseIf, Regex::new(r"^else if[ ]*").unwrap()),
         (TokenType::Else, Regex::new(r"^else[ ]*").unwrap()),
         (TokenType::And, Regex::new(r"^&&[ ]*").unwrap()),
         (TokenType::Or, Regex::new(r"^[|][|][ ]*").unwrap()),
+        (TokenType::Pipe, Regex::new(r"^[|][ ]*").unwrap()),
+        (TokenType::BitAnd, Regex::new(r"^[&][ ]*").unwrap()),
+        (TokenType::BitXor, Regex::new(r"^\^[ ]*").unwrap()),
+        (TokenType::Ellipsis, Regex::new(r"^[.][.][.][ ]*").unwrap()),
+        (TokenType::DotDot, Regex::new(r"^[.][.][ ]*").unwrap()),
         (
             TokenType::Float,
-            Regex::new(r"^[-]?([\d ]*[.])[\d ]*").unwrap(),
+            Regex::new(r"^[-]?\d*[.]\d+[ ]*").unwrap(),
         ),
         (TokenType::Number, Regex::new(r"^[-]?\d[\d ]*").unwrap()),
         (
             TokenType::FormattedQuote,
-            Regex::new(r#"^[f]["][ ]*"#).unwrap(),
+            Regex::new(r#"^[f]["]"#).unwrap(),
         ),
         (
             TokenType::CommandQuote,
-            Regex::new(r#"^[c]["][ ]*"#).unwrap(),
+            Regex::new(r#"^[c]["]"#).unwrap(),
         ),
-        (TokenType::SingleQuote, Regex::new(r"^['][ ]*").unwrap()),
-        (TokenType::DoubleQuote, Regex::new(r#"^["][ ]*"#).unwrap()),
+        (TokenType::SingleQuote, Regex::new(r"^[']").unwrap()),
+        (TokenType::DoubleQuote, Regex::new(r#"^["]"#).unwrap()),
         (TokenType::Bool, Regex::new(r"^true[ ]*").unwrap()),
         (TokenType::Bool, Regex::new(r"^false[ ]*").unwrap()),
+        (TokenType::Nil, Regex::new(r"^nil\b[ ]*").unwrap()),
+        (TokenType::PlusEquals, Regex::new(r"^[+]=[ ]*").unwrap()),
+        (TokenType::MinusEquals, Regex::new(r"^[-]=[ ]*").unwrap()),
+        (TokenType::Power, Regex::new(r"^[*][*][ ]*").unwrap()),
+        (TokenType::TimesEquals, Regex::new(r"^[*]=[ ]*").unwrap()),
+        (TokenType::DivideEquals, Regex::new(r"^[/]=[ ]*").unwrap()),
+        (TokenType::ModuloEquals, Regex::new(r"^[%]=[ ]*").unwrap()),
+        (TokenType::Modulo, Regex::new(r"^[%][ ]*").unwrap()),
         (TokenType::PlusPlus, Regex::new(r"^[+][+][ ]*").unwrap()),
         (TokenType::Plus, Regex::new(r"^[+][ ]*").unwrap()),
         (TokenType::MinusMinus, Regex::new(r"^[-][-][ ]*").unwrap()),
@@ -110,23 +171,31 @@ static KEYWORDS: Lazy<[(TokenType, Regex); 51]> = Lazy::new(|| {
         (TokenType::Divide, Regex::new(r"^[/][ ]*").unwrap()),
         (TokenType::EqualLesser, Regex::new(r"^<=[ ]*").unwrap()),
         (TokenType::EqualGreater, Regex::new(r"^>=[ ]*").unwrap()),
+        (TokenType::ShiftLeft, Regex::new(r"^<<[ ]*").unwrap()),
+        (TokenType::ShiftRight, Regex::new(r"^>>[ ]*").unwrap()),
         (TokenType::LesserThan, Regex::new(r"^[<][ ]*").unwrap()),
         (TokenType::GreaterThan, Regex::new(r"^[>][ ]*").unwrap()),
         (TokenType::EqualTo, Regex::new(r"^==[ ]*").unwrap()),
+        (TokenType::FatArrow, Regex::new(r"^=>[ ]*").unwrap()),
         (TokenType::NotEqual, Regex::new(r"^!=[ ]*").unwrap()),
         (TokenType::Not, Regex::new(r"^![ ]*").unwrap()),
         (TokenType::Equals, Regex::new(r"^=[ ]*").unwrap()),
         (TokenType::Semicolon, Regex::new(r"^[;][ ]*").unwrap()),
+        (TokenType::Colon, Regex::new(r"^[:][ ]*").unwrap()),
+        (TokenType::Coalesce, Regex::new(r"^[?][?][ ]*").unwrap()),
+        (TokenType::OptionalIndex, Regex::new(r"^[?]\[[ ]*").unwrap()),
+        (TokenType::Question, Regex::new(r"^[?][ ]*").unwrap()),
         (TokenType::OpeningBrace, Regex::new(r"^[{][ ]*").unwrap()),
         (TokenType::ClosingBrace, Regex::new(r"^[}][ ]*").unwrap()),
         (TokenType::OpeningBracket, Regex::new(r"^[(][ ]*").unwrap()),
         (TokenType::ClosingBracket, Regex::new(r"^[)][ ]*").unwrap()),
         (TokenType::OpeningSquareBracket, Regex::new(r"^\[[ ]*").unwrap()),
         (TokenType::ClosingSquareBracket, Regex::new(r"^\][ ]*").unwrap()),
-        (TokenType::Function, Regex::new(r"(^fn[ ]*)").unwrap()),
+        (TokenType::Function, Regex::new(r"(^fn\b[ ]*)").unwrap()),
         (
             TokenType::Name,
-            Regex::new(r"(?<name>[A-Za-z_\d ]*)").unwrap(),
+            // `module.function` is one name, it's how namespaced imports are called
+            Regex::new(r"^(?<name>(?:[A-Za-z_\d]+(?:[.][A-Za-z_][A-Za-z_\d]*)*)?)").unwrap(),
         ),
     ]
 });
@@ -154,7 +223,11 @@ pub fn tokenize(content: &str) -> Vec<Token> {
     let mut iterator = content.trim();
     
     while iterator != "" {
-        iterator = &iterator.trim()[cursor..].trim();
+        // Whitespace inside a string literal is part of its content
+        iterator = &iterator[cursor..];
+        if !string_starter {
+            iterator = iterator.trim();
+        }
         if iterator == "" {
             break;
         }
@@ -1,3 +1,8 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
 use crate::parsing::lexer::{Token, TokenType};
 
 #[derive(Debug, Clone)]
@@ -7,7 +12,7 @@ pub enum Expr {
     Block(Block),
     Literal(DataType),
     Function(Function),
-    Definition(Definition),
+    Definition(Rc<Definition>),
     Nil,
 }
 
@@ -24,19 +29,77 @@ pub struct DataType {
 pub struct DataStore {
     pub integer: Option<i32>,
     pub bool: Option<bool>,
-    pub array: Option<Vec<Box<Expr>>>
+    pub float: Option<f64>,
+    // Shared between copies of the value, it's only copied when one of them gets changed
+    pub array: Option<Rc<Vec<Box<Expr>>>>,
+    pub function: Option<Rc<Closure>>,
+}
+
+// A function used as a value, with the variables it used from where it was made.
+// They are kept between calls, so a closure can hold on to its own state
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub definition: Rc<Definition>,
+    pub captured: RefCell<HashMap<String, DataType>>,
+}
+
+// The top level variables of an imported module, its functions run with these as their globals
+pub struct ModuleScope {
+    pub path: String,
+    pub globals: RefCell<HashMap<String, DataType>>,
+}
+
+// The globals can hold the module's own functions, printing them would never end
+impl fmt::Debug for ModuleScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "ModuleScope({})", self.path);
+    }
 }
 
 impl DataType {
     pub fn new() -> Self {
-        return DataType{kind: Literal::Nil, value: "".to_string(), store: DataStore::new(None, None)};
+        return DataType{kind: Literal::Nil, value: "nil".to_string(), store: DataStore::new(None, None)};
+    }
+
+    pub fn string(value: String) -> Self {
+        return DataType{kind: Literal::String, value, store: DataStore::new(None, None)};
+    }
+
+    pub fn int(integer: i32) -> Self {
+        return DataType{kind: Literal::Int, value: integer.to_string(), store: DataStore::new(Some(integer), None)};
+    }
+
+    pub fn float(float: f64) -> Self {
+        let mut data = DataType{kind: Literal::Float, value: format!("{:?}", float), store: DataStore::new(None, None)};
+        data.store.float = Some(float);
+        return data;
+    }
+
+    pub fn function(closure: Closure) -> Self {
+        let mut value = "<fn>".to_string();
+        if !closure.definition.name.is_empty() {
+            value = format!("<fn {}>", short_name(&closure.definition.name));
+        }
+        let mut data = DataType{kind: Literal::Function, value, store: DataStore::new(None, None)};
+        data.store.function = Some(Rc::new(closure));
+        return data;
+    }
+
+    pub fn bool(b: bool) -> Self {
+        return DataType{kind: Literal::Bool, value: b.to_string(), store: DataStore::new(None, Some(b))};
+    }
+
+    pub fn array(array: Vec<Box<Expr>>) -> Self {
+        let mut data = DataType{kind: Literal::Array, value: "".to_string(), store: DataStore::new(None, None)};
+        data.store.array = Some(Rc::new(array));
+        return data;
     }
 }
 
 
 impl DataStore {
     pub fn new(integer: Option<i32>, bool: Option<bool>) -> Self {
-        return DataStore{integer, bool, array: None};
+        return DataStore{integer, bool, float: None, array: None, function: None};
     }
 }
 
@@ -63,11 +126,24 @@ pub enum Operator {
     Minus,
     Times,
     Divide,
+    FloorDivide,
     And,
     Or,
     Not,
     NotEqual,
     Index,
+    OptionalIndex,
+    Coalesce,
+    Range,
+    Negate,
+    Modulo,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
     Nil,
 }
 
@@ -91,11 +167,24 @@ pub enum BlockType {
     If,
     Else,
     ElseIf,
+    IfChain,
+    Match,
+    MatchArm,
+    Conditional,
     For,
+    ForIn,
+    While,
+    Loop,
+    Try,
+    Catch,
+    Break,
+    Continue,
     FormatedString,
     CommandString,
     Import,
     Return,
+    Let,
+    Global,
     Nil,
 }
 
@@ -104,11 +193,12 @@ pub struct Block {
     pub kind: BlockType,
     pub conditions: Vec<Box<Expr>>,
     pub block: Vec<Box<Expr>>,
+    pub label: Option<String>,
 }
 
 impl Block {
     pub fn new() -> Self {
-        return Block{kind: BlockType::Nil, conditions: Vec::new(), block: Vec::new()};
+        return Block{kind: BlockType::Nil, conditions: Vec::new(), block: Vec::new(), label: None};
     }  
 }
 
@@ -122,10 +212,85 @@ pub enum FunctionType {
     Delete,
     Int,
     String,
+    Slice,
+    Split,
+    Join,
+    Trim,
+    Replace,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Upper,
+    Lower,
+    Lines,
+    Exit,
+    Bool,
+    IsNil,
+    Type,
+    Float,
+    Array,
+    Map,
+    Filter,
+    Reduce,
+    Sort,
+    SortBy,
+    Find,
+    Any,
+    All,
+    Zip,
+    Enumerate,
+    Reverse,
+    Unique,
+    Call,
     Defined,
     Nil,
 }
 
+// Builtins without a keyword of their own are called like any defined function
+pub fn builtin(name: &str) -> FunctionType {
+    match name {
+        "split" => {return FunctionType::Split},
+        "join" => {return FunctionType::Join},
+        "trim" => {return FunctionType::Trim},
+        "replace" => {return FunctionType::Replace},
+        "contains" => {return FunctionType::Contains},
+        "starts_with" => {return FunctionType::StartsWith},
+        "ends_with" => {return FunctionType::EndsWith},
+        "upper" => {return FunctionType::Upper},
+        "lower" => {return FunctionType::Lower},
+        "lines" => {return FunctionType::Lines},
+        "exit" => {return FunctionType::Exit},
+        "bool" => {return FunctionType::Bool},
+        "is_nil" => {return FunctionType::IsNil},
+        "type" => {return FunctionType::Type},
+        "float" => {return FunctionType::Float},
+        "array" => {return FunctionType::Array},
+        "map" => {return FunctionType::Map},
+        "filter" => {return FunctionType::Filter},
+        "reduce" => {return FunctionType::Reduce},
+        "sort" => {return FunctionType::Sort},
+        "sort_by" => {return FunctionType::SortBy},
+        "find" => {return FunctionType::Find},
+        "any" => {return FunctionType::Any},
+        "all" => {return FunctionType::All},
+        "zip" => {return FunctionType::Zip},
+        "enumerate" => {return FunctionType::Enumerate},
+        "reverse" => {return FunctionType::Reverse},
+        "unique" => {return FunctionType::Unique},
+        _ => {return FunctionType::Defined},
+    }
+}
+
+// Builtins from the table above, a function defined with the same name replaces them
+pub fn named_builtin(call: &Function) -> bool {
+    return !matches!(call.kind, FunctionType::Defined | FunctionType::Call) && !matches!(builtin(&call.name), FunctionType::Defined);
+}
+
+// Functions from modules are registered as `path::name`, this is the name they were written with
+pub fn short_name(name: &str) -> &str {
+    return name.rsplit("::").next().unwrap_or(name);
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
     pub kind: FunctionType,
@@ -140,15 +305,21 @@ pub struct Definition {
     pub arguments: Vec<Box<Expr>>,
     pub block: Vec<Box<Expr>>,
     pub returns: Option<DataType>,
+    // `...name` as the last parameter collects the extra arguments into an array
+    pub rest: Option<String>,
+    // Marked with `export`, once a module marks any function only those can be imported from it
+    pub export: bool,
+    // The module it was defined in, None for the script being run
+    pub module: Option<Rc<ModuleScope>>,
 }
 
 impl Definition {
     pub fn new() -> Self {
-        return Definition{name: "".to_string(), arguments: Vec::new(), block: Vec::new(), returns: None};
+        return Definition{name: "".to_string(), arguments: Vec::new(), block: Vec::new(), returns: None, rest: None, export: false, module: None};
     }  
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub enum Literal {
     String,
     Variable,
@@ -156,6 +327,7 @@ pub enum Literal {
     Float,
     Bool,
     Array,
+    Function,
     Nil,
 }
 
@@ -183,87 +355,87 @@ pub fn parse_any(tokens: Vec<Token>, tree: &mut Vec<Box<Expr>>, conditions: bool
             TokenType::ClosingBrace => {
                 break;
             }
-            TokenType::Semicolon | TokenType::Newline | TokenType::ClosingBracket | TokenType::ClosingSquareBracket => {
+            TokenType::Semicolon | TokenType::Newline | TokenType::ClosingBracket | TokenType::ClosingSquareBracket | TokenType::Colon => {
                 if !block {
                     break;
                 }
             }
-            TokenType::SingleQuote | TokenType::DoubleQuote => {
+            TokenType::Name | TokenType::Content if is_assignment(&tokens[i..]) => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_string(tokens[i..].to_vec());
+                (expr, j) = parse_variable(tokens[i+1..].to_vec(), value.to_string());
                 tree.push(Box::new(expr));
-                i += j-1;
+                i += j;
             }
-            TokenType::CommandQuote | TokenType::FormattedQuote => {
+            TokenType::Name | TokenType::Content | TokenType::Number | TokenType::Float | TokenType::Bool | TokenType::Nil | TokenType::SingleQuote | TokenType::DoubleQuote | TokenType::CommandQuote | TokenType::FormattedQuote | TokenType::OpeningSquareBracket | TokenType::OpeningBracket | TokenType::Minus | TokenType::Not | TokenType::Tilda | TokenType::Match | TokenType::Print | TokenType::Length | TokenType::Push | TokenType::Pop | TokenType::Swap | TokenType::Delete | TokenType::Int | TokenType::String => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_fstring(tokens[i..].to_vec());
+                (expr, j) = parse_expr(tokens[i..].to_vec(), 0);
                 tree.push(Box::new(expr));
                 i += j-1;
             }
-            TokenType::Name | TokenType::Content => {
+            TokenType::If | TokenType::ElseIf | TokenType::Else | TokenType::For | TokenType::While | TokenType::Loop | TokenType::Try | TokenType::Return => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_variable(tokens[i+1..].to_vec(), value.to_string());
+                (expr, j) = parse_block(tokens[i..].to_vec());
                 tree.push(Box::new(expr));
                 i += j;
             }
-            TokenType::OpeningSquareBracket => {
+            TokenType::Break | TokenType::Continue => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_array(tokens[i..].to_vec());
+                (expr, j) = parse_jump(tokens[i..].to_vec());
                 tree.push(Box::new(expr));
                 i += j;
             }
-            TokenType::If | TokenType::ElseIf | TokenType::Else | TokenType::For | TokenType::Import | TokenType::Return => {
+            TokenType::Let | TokenType::Global => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_block(tokens[i..].to_vec());
+                (expr, j) = parse_declaration(tokens[i..].to_vec());
                 tree.push(Box::new(expr));
                 i += j;
             }
-
-            TokenType::PlusPlus | TokenType::MinusMinus => {
+            TokenType::Import | TokenType::From => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_un(tokens[i..].to_vec());
+                (expr, j) = parse_import(tokens[i..].to_vec());
                 tree.push(Box::new(expr));
                 i += j;
             }
-            TokenType::Equals | TokenType::EqualTo | TokenType::LesserThan | TokenType::GreaterThan | TokenType::EqualGreater | TokenType::EqualLesser | TokenType::Plus | TokenType::And | TokenType::Or | TokenType::Not | TokenType::NotEqual => {
+            TokenType::Export if matches!(tokens.get(i+1).map(|token| token.kind), Some(TokenType::Function)) => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_bin(tokens[i..].to_vec(), *tree[tree.len()-1].clone());
-                tree.pop();
-                tree.push(Box::new(expr));
-                i += j;
-            }
-            TokenType::Number => {
-                let integer: i32 = value.to_string().parse().expect("INCORRECT INTEGER");
-                let data = DataType{value: value.to_string(), kind: Literal::Int, store: DataStore::new(Some(integer), None)};
-                let expr = Expr::Literal(data);
-                tree.push(Box::new(expr));
+                (expr, j) = parse_definition(tokens[i+2..].to_vec());
+                match expr {
+                    Expr::Definition(mut definition) => {
+                        Rc::make_mut(&mut definition).export = true;
+                        tree.push(Box::new(Expr::Definition(definition)));
+                    }
+                    _ => {}
+                }
+                i += j + 2;
             }
-            TokenType::Bool => {
-                let b: bool = value.to_string().parse().expect("INCORRECT BOOLEAN");
-                let data = DataType{value: value.to_string(), kind: Literal::Bool, store: DataStore::new(None, Some(b))};
-                let expr = Expr::Literal(data);
+
+            TokenType::PlusPlus | TokenType::MinusMinus => {
+                let j: usize;
+                let expr: Expr;
+                (expr, j) = parse_un(tokens[i..].to_vec());
                 tree.push(Box::new(expr));
+                i += j;
             }
-            TokenType::Print | TokenType::Length | TokenType::Push | TokenType::Pop | TokenType::Swap | TokenType::Delete | TokenType::Int | TokenType::String => {
+            TokenType::Function if matches!(tokens.get(i+1).map(|token| token.kind), Some(TokenType::OpeningBracket)) => {
                 let j: usize;
                 let expr: Expr;
-                (expr, j) = parse_function(tokens[i..].to_vec(), "print".to_string());
+                (expr, j) = parse_expr(tokens[i..].to_vec(), 0);
                 tree.push(Box::new(expr));
-                i += j;
+                i += j-1;
             }
             TokenType::Function => {
                 let j: usize;
                 let expr: Expr;
                 (expr, j) = parse_definition(tokens[i+1..].to_vec());
                 tree.push(Box::new(expr));
-                i += j;
+                i += j + 1;
             }
             _ => {}
         }
@@ -279,7 +451,7 @@ pub fn parse_string(tokens: Vec<Token>) -> (Expr, usize) {
         TokenType::CommandQuote => {block_kind = BlockType::CommandString}
         _ => {}, 
     }
-    let mut block: Block = Block{kind: block_kind, block: Vec::new(), conditions: Vec::new()};
+    let mut block: Block = Block{kind: block_kind, block: Vec::new(), conditions: Vec::new(), label: None};
     let mut i:usize = 1;
     let mut content: String = "".to_string();
     while i < tokens.len() { 
@@ -312,7 +484,7 @@ pub fn parse_fstring(tokens: Vec<Token>) -> (Expr, usize) {
         TokenType::CommandQuote => {block_kind = BlockType::CommandString}
         _ => {}, 
     }
-    let mut block: Block = Block{kind: block_kind, block: Vec::new(), conditions: Vec::new()};
+    let mut block: Block = Block{kind: block_kind, block: Vec::new(), conditions: Vec::new(), label: None};
     let mut i:usize = 1;
     while i < tokens.len() { 
         let value = (&tokens[i].value).to_string();
@@ -335,259 +507,293 @@ pub fn parse_fstring(tokens: Vec<Token>) -> (Expr, usize) {
 }
 
 pub fn parse_variable(tokens: Vec<Token>, name: String) -> (Expr, usize) {
-    let mut operator: Operator = Operator::Nil;
+    let data = DataType{value: name.clone(), kind: Literal::Variable, store: DataStore::new(None, None)};
+    let mut compound: Option<Operator> = None;
+    if tokens.is_empty() {
+        return (Expr::Literal(data), 0);
+    }
     match tokens[0].kind {
-        TokenType::Plus=>{operator = Operator::Plus},
-        TokenType::Minus=>{operator = Operator::Minus},
-        TokenType::Times=>{operator = Operator::Times},
-        TokenType::Divide=>{operator = Operator::Divide},
-        TokenType::Equals=>{operator = Operator::Equals},
-        TokenType::EqualTo=>{operator = Operator::EqualTo},
-        TokenType::EqualGreater=>{operator = Operator::EqualGreater},
-        TokenType::EqualLesser=>{operator = Operator::EqualLesser},
-        TokenType::LesserThan=>{operator = Operator::LesserThan},
-        TokenType::GreaterThan=>{operator = Operator::GreaterThan},
-        TokenType::And=>{operator = Operator::And},
-        TokenType::Not=>{operator = Operator::Not},
-        TokenType::NotEqual=>{operator = Operator::NotEqual},
-        TokenType::OpeningBracket => {
-            return parse_function(tokens[0..].to_vec(), name);
-        },
-        TokenType::OpeningSquareBracket => {
-            operator = Operator::Index;
+        TokenType::Equals => {},
+        TokenType::PlusEquals => {compound = Some(Operator::Plus)},
+        TokenType::MinusEquals => {compound = Some(Operator::Minus)},
+        TokenType::TimesEquals => {compound = Some(Operator::Times)},
+        TokenType::DivideEquals => {compound = Some(Operator::Divide)},
+        TokenType::ModuloEquals => {compound = Some(Operator::Modulo)},
+        TokenType::Colon => {
+            // `name: for ...` labels a loop so `break name` can leave it from a nested one
+            match tokens.get(1).map(|token| token.kind) {
+                Some(TokenType::For) | Some(TokenType::While) | Some(TokenType::Loop) => {
+                    let (expr, j) = parse_block(tokens[1..].to_vec());
+                    let Expr::Block(mut block) = expr else { return (expr, j+2); };
+                    block.label = Some(name);
+                    return (Expr::Block(block), j+2);
+                }
+                _ => {
+                    return (Expr::Literal(data), 0);
+                }
+            }
         }
         _ => {
-            let data = DataType{value: name, kind: Literal::Variable, store: DataStore::new(None, None)};
             return (Expr::Literal(data), 0);
         }, 
     }
-    let mut expr: Expr = Expr::Nil; 
-    let mut i:usize = 1;
-    while i < tokens.len() { 
-        let value = (&tokens[i].value).to_string();
-        match tokens[i].kind {
-            TokenType::Semicolon | TokenType::Newline | TokenType::OpeningBrace | TokenType::ClosingBrace | TokenType::ClosingBracket | TokenType::ClosingSquareBracket | TokenType::Comma => {
-                break;
-            }
-            TokenType::Name | TokenType::Content => {
-                let j: usize;
-                (expr, j) = parse_variable(tokens[i+1..].to_vec(), value.to_string());
-                i += j;
-            }
-            TokenType::OpeningSquareBracket => {
-                let j: usize;
-                (expr, j) = parse_array(tokens[i..].to_vec());
-                i += j;
-            }
-            TokenType::SingleQuote | TokenType::DoubleQuote => {
-                let j: usize;
-                (expr, j) = parse_string(tokens[i..].to_vec());
-                i += j-1;
-            }
-            TokenType::CommandQuote | TokenType::FormattedQuote => {
-                let j: usize;
-                (expr, j) = parse_fstring(tokens[i..].to_vec());
-                i += j;
-            }
-            TokenType::Number => {
-                let integer: i32 = value.parse().expect("INCORRECT INTEGER");
-                let data = DataType{value, kind: Literal::Int, store: DataStore::new(Some(integer), None)};
-                expr = Expr::Literal(data);
-            }
-            TokenType::Bool => {
-                let b: bool = value.parse().expect("INCORRECT BOOLEAN");
-                let data = DataType{value, kind: Literal::Bool, store: DataStore::new(None, Some(b))};
-                expr = Expr::Literal(data);
-            }
-            TokenType::Length | TokenType::Pop | TokenType::Push | TokenType::Swap | TokenType::Int | TokenType::String | TokenType::Delete => {
-                let j: usize;
-                (expr, j) = parse_function(tokens[i..].to_vec(), "length".to_string());
-                i += j;
-            }
-            TokenType::Plus | TokenType::Minus | TokenType::Times | TokenType::Divide | TokenType::EqualTo | TokenType::LesserThan | TokenType::GreaterThan | TokenType::EqualLesser | TokenType::EqualGreater | TokenType::Not | TokenType::NotEqual | TokenType::And => {
-                let j: usize;
-                (expr, j) = parse_bin(tokens[i..].to_vec(), expr);
-                i += j;
-            }
-            _ => {
+    let (expr, j) = parse_expr(tokens[1..].to_vec(), 0);
+    let left = Box::new(Expr::Literal(data)); 
+    let mut right = Box::new(expr);
+    // `x += y` is stored as `x = x + y`
+    match compound {
+        Some(compound_operator) => {
+            right = Box::new(Expr::Binary(BinaryExpr{operator: compound_operator, left: left.clone(), right}));
+        }
+        None => {}
+    }
+    let bin_expr: BinaryExpr = BinaryExpr{operator: Operator::Equals, left, right};
+    return (Expr::Binary(bin_expr), j+1);
+}
 
+// Whether a statement starting with a name assigns to it or labels a loop rather than being an expression
+fn is_assignment(tokens: &[Token]) -> bool {
+    match tokens.get(1).map(|token| token.kind) {
+        Some(TokenType::Equals) | Some(TokenType::PlusEquals) | Some(TokenType::MinusEquals) | Some(TokenType::TimesEquals) | Some(TokenType::DivideEquals) | Some(TokenType::ModuloEquals) => {return true;}
+        Some(TokenType::Colon) => {
+            match tokens.get(2).map(|token| token.kind) {
+                Some(TokenType::For) | Some(TokenType::While) | Some(TokenType::Loop) => {return true;}
+                _ => {return false;}
             }
         }
-        i += 1;
+        _ => {return false;}
     }
-    let data = DataType{value: name, kind: Literal::Variable, store: DataStore::new(None, None)};
-    let left = Box::new(Expr::Literal(data)); 
-    let right = Box::new(expr);
-    let bin_expr: BinaryExpr = BinaryExpr{operator, left, right};
-    return (Expr::Binary(bin_expr), i);
 }
 
-pub fn parse_bin(tokens: Vec<Token>, left: Expr) -> (Expr, usize) {
-    let mut operator: Operator = Operator::Nil;
-    match tokens[0].kind {
-        TokenType::Plus=>{operator = Operator::Plus},
-        TokenType::Minus=>{operator = Operator::Minus},
-        TokenType::Times=>{operator = Operator::Times},
-        TokenType::Divide=>{operator = Operator::Divide},
-        TokenType::Equals=>{operator = Operator::Equals},
-        TokenType::EqualTo=>{operator = Operator::EqualTo},
-        TokenType::EqualGreater=>{operator = Operator::EqualGreater},
-        TokenType::EqualLesser=>{operator = Operator::EqualLesser},
-        TokenType::LesserThan=>{operator = Operator::LesserThan},
-        TokenType::GreaterThan=>{operator = Operator::GreaterThan},
-        TokenType::And=>{operator = Operator::And},
-        TokenType::Not=>{operator = Operator::Not},
-        TokenType::NotEqual=>{operator = Operator::NotEqual},
-        _ => {}, 
+const TERNARY_POWER: u8 = 1;
+const PREFIX_POWER: u8 = 23;
+const INDEX_POWER: u8 = 27;
+
+// Left and right binding power of every infix operator, a higher power binds tighter and a right power above the left one makes it left associative
+fn infix_power(kind: TokenType) -> Option<(Operator, u8, u8)> {
+    match kind {
+        TokenType::DotDot => {return Some((Operator::Range, 1, 2));}
+        TokenType::Coalesce => {return Some((Operator::Coalesce, 2, 3));}
+        TokenType::Or => {return Some((Operator::Or, 3, 4));}
+        TokenType::And => {return Some((Operator::And, 5, 6));}
+        TokenType::EqualTo => {return Some((Operator::EqualTo, 7, 8));}
+        TokenType::NotEqual => {return Some((Operator::NotEqual, 7, 8));}
+        TokenType::LesserThan => {return Some((Operator::LesserThan, 9, 10));}
+        TokenType::GreaterThan => {return Some((Operator::GreaterThan, 9, 10));}
+        TokenType::EqualLesser => {return Some((Operator::EqualLesser, 9, 10));}
+        TokenType::EqualGreater => {return Some((Operator::EqualGreater, 9, 10));}
+        TokenType::Pipe => {return Some((Operator::BitOr, 11, 12));}
+        TokenType::BitXor => {return Some((Operator::BitXor, 13, 14));}
+        TokenType::BitAnd => {return Some((Operator::BitAnd, 15, 16));}
+        TokenType::ShiftLeft => {return Some((Operator::ShiftLeft, 17, 18));}
+        TokenType::ShiftRight => {return Some((Operator::ShiftRight, 17, 18));}
+        TokenType::Plus => {return Some((Operator::Plus, 19, 20));}
+        TokenType::Minus => {return Some((Operator::Minus, 19, 20));}
+        TokenType::Times => {return Some((Operator::Times, 21, 22));}
+        TokenType::Divide => {return Some((Operator::Divide, 21, 22));}
+        TokenType::FloorDivide => {return Some((Operator::FloorDivide, 21, 22));}
+        TokenType::Modulo => {return Some((Operator::Modulo, 21, 22));}
+        // `**` is right associative and binds tighter than a unary minus in front of it
+        TokenType::Power => {return Some((Operator::Power, 26, 25));}
+        _ => {return None;}
     }
-    let mut bin = BinaryExpr{operator, left: Box::new(left), right: Box::new(Expr::Nil)};
-    let mut i:usize = 1;
-    while i < tokens.len() { 
-        let value = (&tokens[i].value).to_string();
-        match tokens[i].kind {
-            TokenType::Semicolon | TokenType::Newline | TokenType::OpeningBrace | TokenType::ClosingBrace | TokenType::Comma => {
-                i -= 1;
-                break;
-            }
-            TokenType::SingleQuote | TokenType::DoubleQuote | TokenType::CommandQuote | TokenType::FormattedQuote => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_string(tokens[i..].to_vec());
-                bin.right = Box::new(expr);
-                i += j-1;
-            }
+}
 
-            TokenType::OpeningSquareBracket => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_array(tokens[i..].to_vec());
-                bin.right = Box::new(expr);
-                i += j;
-            }
-            TokenType::Name => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_variable(tokens[i+1..].to_vec(), value.to_string());
-                bin.right = Box::new(expr);
-                i += j;
-            }
-            TokenType::Bool => {
-                let b: bool = value.parse().expect("INCORRECT BOOLEAN");
-                let data = DataType{value, kind: Literal::Bool, store: DataStore::new(None, Some(b))};
-                bin.right = Box::new(Expr::Literal(data));
-            }
-            TokenType::Number => {
-                let integer: i32 = value.parse().expect("INCORRECT INTEGER");
-                let data = DataType{value, kind: Literal::Int, store: DataStore::new(Some(integer), None)};
-                bin.right = Box::new(Expr::Literal(data));
+// Parses one expression, only operators binding tighter than min_power are taken in, returns how many tokens were used
+pub fn parse_expr(tokens: Vec<Token>, min_power: u8) -> (Expr, usize) {
+    let (mut left, mut i) = parse_prefix(tokens.clone());
+    if i == 0 {
+        return (left, 0);
+    }
+    while i < tokens.len() {
+        match tokens[i].kind {
+            // Anything that gives a function back can be called straight away, like `adders[0](1)`
+            TokenType::OpeningBracket => {
+                if INDEX_POWER < min_power {
+                    break;
+                }
+                let (expr, j) = parse_function(tokens[i..].to_vec(), "".to_string());
+                let Expr::Function(mut call) = expr else { break; };
+                call.kind = FunctionType::Call;
+                call.arguments.insert(0, Box::new(left));
+                left = Expr::Function(call);
+                i += j + 1;
+                continue;
             }
-
-            TokenType::Length | TokenType::Pop | TokenType::Push | TokenType::Swap | TokenType::Int | TokenType::String | TokenType::Delete => {
+            TokenType::OpeningSquareBracket | TokenType::OptionalIndex => {
+                if INDEX_POWER < min_power {
+                    break;
+                }
                 let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_function(tokens[i..].to_vec(), "length".to_string());
-                bin.right = Box::new(expr);
+                (left, j) = parse_index(tokens[i..].to_vec(), left);
                 i += j;
+                continue;
             }
-            TokenType::And | TokenType::Or => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = and_or(tokens[i..].to_vec(), Expr::Binary(bin.clone()));
-                match expr {
-                    Expr::Binary(x) => {
-                        bin = x;
+            // `condition ? then : otherwise` binds loosest of all and nests to the right
+            TokenType::Question => {
+                if TERNARY_POWER < min_power {
+                    break;
+                }
+                let mut block = Block::new();
+                block.kind = BlockType::Conditional;
+                block.conditions.push(Box::new(left));
+                let (then, j) = parse_expr(tokens[i+1..].to_vec(), 0);
+                block.block.push(Box::new(then));
+                i += j + 1;
+                match tokens.get(i).map(|token| token.kind) {
+                    Some(TokenType::Colon) => {
+                        let (otherwise, j) = parse_expr(tokens[i+1..].to_vec(), TERNARY_POWER);
+                        block.block.push(Box::new(otherwise));
+                        i += j + 1;
+                    }
+                    _ => {
+                        block.block.push(Box::new(Expr::Nil));
                     }
-                    _ => {}
                 }
-                i += j;
-
+                left = Expr::Block(block);
+                continue;
             }
-            TokenType::Equals | TokenType::EqualTo | TokenType::LesserThan | TokenType::GreaterThan | TokenType::EqualGreater | TokenType::EqualLesser | TokenType::Plus | TokenType::Not | TokenType::NotEqual => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_bin(tokens[i..].to_vec(), Expr::Binary(bin.clone()));
-                match expr {
-                    Expr::Binary(x) => {
-                        bin = x;
-                    }
-                    _ => {}
+            // `a -1` is lexed as a negative number, it's still a subtraction
+            TokenType::Number | TokenType::Float if tokens[i].value.starts_with("-") => {
+                let (_, left_power, right_power) = infix_power(TokenType::Minus).unwrap();
+                if left_power < min_power {
+                    break;
                 }
+                let mut rest = tokens[i..].to_vec();
+                rest[0].value = rest[0].value[1..].trim().to_string();
+                let (right, j) = parse_expr(rest, right_power);
+                left = Expr::Binary(BinaryExpr{operator: Operator::Minus, left: Box::new(left), right: Box::new(right)});
                 i += j;
+                continue;
             }
-            _ => {
-
-            }
+            _ => {}
         }
-        i += 1;
+        let Some((operator, left_power, right_power)) = infix_power(tokens[i].kind) else { break; };
+        if left_power < min_power {
+            break;
+        }
+        let (right, j) = parse_expr(tokens[i+1..].to_vec(), right_power);
+        left = Expr::Binary(BinaryExpr{operator, left: Box::new(left), right: Box::new(right)});
+        i += j + 1;
     }
-    return (Expr::Binary(bin), i);
+    return (left, i);
 }
 
-pub fn and_or(tokens: Vec<Token>, left: Expr) -> (Expr, usize) {
-    let mut operator: Operator = Operator::Nil;
-    match tokens[0].kind {
-        TokenType::And=>{operator = Operator::And},
-        TokenType::Or=>{operator = Operator::Or},
-        _ => {}, 
+// The operand an expression starts with, together with any unary operator in front of it
+fn parse_prefix(tokens: Vec<Token>) -> (Expr, usize) {
+    if tokens.is_empty() {
+        return (Expr::Nil, 0);
     }
-    let mut bin = BinaryExpr{operator, left: Box::new(left), right: Box::new(Expr::Nil)};
-    let mut i:usize = 1;
-    
-
-    while i < tokens.len() { 
-        let value = (&tokens[i].value).to_string();
-        match tokens[i].kind {
-            TokenType::Semicolon | TokenType::Newline | TokenType::OpeningBrace => {
-                break;
-            }
-            TokenType::Name => {
-                let data = DataType{value, kind: Literal::Variable, store: DataStore::new(None, None)};
-                bin.right = Box::new(Expr::Literal(data));
-            }
-            TokenType::SingleQuote | TokenType::DoubleQuote | TokenType::CommandQuote | TokenType::FormattedQuote => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_string(tokens[i..].to_vec());
-                bin.right = Box::new(expr);
-                i += j-1;
-            }
-            TokenType::Number => {
-                let integer: i32 = value.parse().expect("INCORRECT INTEGER");
-                let data = DataType{value, kind: Literal::Int, store: DataStore::new(Some(integer), None)};
-                bin.right = Box::new(Expr::Literal(data));
-            }
-            TokenType::Bool => {
-                let b: bool = value.parse().expect("INCORRECT BOOLEAN");
-                let data = DataType{value, kind: Literal::Bool, store: DataStore::new(None, Some(b))};
-                bin.right = Box::new(Expr::Literal(data));
+    let value = tokens[0].value.trim().to_string();
+    match tokens[0].kind {
+        TokenType::Number => {
+            // The lexer keeps a minus on the number, `-2 ** 2` still has to negate the power
+            if value.starts_with("-") && matches!(tokens.get(1).map(|token| token.kind), Some(TokenType::Power)) {
+                let mut rest = tokens.clone();
+                rest[0].value = value[1..].trim().to_string();
+                let (expr, j) = parse_expr(rest, PREFIX_POWER);
+                return (Expr::Unary(UnaryExpr{operator: Operator::Negate, value: Box::new(expr)}), j);
             }
-            TokenType::And | TokenType::Or => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = and_or(tokens[i..].to_vec(), Expr::Binary(bin.clone()));
-                match expr {
-                    Expr::Binary(x) => {
-                        bin = x;
-                    }
-                    _ => {}
+            let integer: i32 = value.parse().expect("INCORRECT INTEGER");
+            let data = DataType{value, kind: Literal::Int, store: DataStore::new(Some(integer), None)};
+            return (Expr::Literal(data), 1);
+        }
+        TokenType::Bool => {
+            let b: bool = value.parse().expect("INCORRECT BOOLEAN");
+            let data = DataType{value, kind: Literal::Bool, store: DataStore::new(None, Some(b))};
+            return (Expr::Literal(data), 1);
+        }
+        TokenType::Float => {
+            let float: f64 = value.parse().expect("INCORRECT FLOAT");
+            return (Expr::Literal(DataType::float(float)), 1);
+        }
+        TokenType::Nil => {
+            return (Expr::Literal(DataType::new()), 1);
+        }
+        TokenType::Name | TokenType::Content => {
+            match tokens.get(1).map(|token| token.kind) {
+                Some(TokenType::OpeningBracket) => {
+                    let (expr, j) = parse_function(tokens[1..].to_vec(), value);
+                    return (expr, j+2);
+                }
+                _ => {
+                    let data = DataType{value, kind: Literal::Variable, store: DataStore::new(None, None)};
+                    return (Expr::Literal(data), 1);
                 }
-                i += j;
-            }
-            TokenType::Equals | TokenType::EqualTo | TokenType::LesserThan | TokenType::GreaterThan | TokenType::EqualGreater | TokenType::EqualLesser | TokenType::Plus | TokenType::Not | TokenType::NotEqual => {
-                let j: usize;
-                let expr: Expr;
-                (expr, j) = parse_bin(tokens[i..].to_vec(), *bin.right);
-                bin.right = Box::new(expr);
-                i += j;
             }
-            _ => {
-
+        }
+        TokenType::SingleQuote | TokenType::DoubleQuote => {
+            return parse_string(tokens);
+        }
+        TokenType::CommandQuote | TokenType::FormattedQuote => {
+            return parse_fstring(tokens);
+        }
+        TokenType::OpeningSquareBracket => {
+            let (expr, j) = parse_array(tokens.clone());
+            return (expr, closer_end(&tokens, j, TokenType::ClosingSquareBracket));
+        }
+        TokenType::OpeningBracket => {
+            let (expr, j) = parse_expr(tokens[1..].to_vec(), 0);
+            return (expr, closer_end(&tokens, j+1, TokenType::ClosingBracket));
+        }
+        TokenType::Minus | TokenType::Not | TokenType::Tilda => {
+            let operator: Operator;
+            match tokens[0].kind {
+                TokenType::Minus => {operator = Operator::Negate;}
+                TokenType::Tilda => {operator = Operator::BitNot;}
+                _ => {operator = Operator::Not;}
             }
+            let (expr, j) = parse_expr(tokens[1..].to_vec(), PREFIX_POWER);
+            return (Expr::Unary(UnaryExpr{operator, value: Box::new(expr)}), j+1);
+        }
+        TokenType::If | TokenType::Try => {
+            let (expr, j) = parse_block(tokens);
+            return (expr, j+1);
+        }
+        TokenType::Match => {
+            let (expr, j) = parse_match(tokens);
+            return (expr, j+1);
+        }
+        TokenType::Print | TokenType::Length | TokenType::Push | TokenType::Pop | TokenType::Swap | TokenType::Delete | TokenType::Int | TokenType::String => {
+            let (expr, j) = parse_function(tokens, value);
+            return (expr, j+1);
+        }
+        // `fn(a, b) { ... }` without a name is a function value
+        TokenType::Function => {
+            let (expr, j) = parse_definition(tokens[1..].to_vec());
+            return (expr, closer_end(&tokens, j+1, TokenType::ClosingBrace));
+        }
+        _ => {
+            return (Expr::Nil, 0);
         }
-        i += 1;
     }
+}
 
-    return (Expr::Binary(bin), i);
+// `target[index]`, `target?[index]` or a slice of it, tokens start at the opening bracket
+fn parse_index(tokens: Vec<Token>, target: Expr) -> (Expr, usize) {
+    if is_slice(&tokens) {
+        return parse_slice(tokens, target);
+    }
+    let mut operator = Operator::Index;
+    match tokens[0].kind {
+        TokenType::OptionalIndex => {operator = Operator::OptionalIndex;}
+        _ => {}
+    }
+    let (index, j) = parse_expr(tokens[1..].to_vec(), 0);
+    let bin_expr = BinaryExpr{operator, left: Box::new(target), right: Box::new(index)};
+    return (Expr::Binary(bin_expr), closer_end(&tokens, j+1, TokenType::ClosingSquareBracket));
+}
+
+// Steps over the semicolons the lexer puts in front of closing brackets and the bracket itself
+fn closer_end(tokens: &Vec<Token>, start: usize, closer: TokenType) -> usize {
+    let mut i = start;
+    while i < tokens.len() && tokens[i].kind == TokenType::Semicolon {
+        i += 1;
+    }
+    if i < tokens.len() && tokens[i].kind == closer {
+        return i + 1;
+    }
+    return start;
 }
 
 pub fn parse_un(tokens: Vec<Token>) -> (Expr, usize) {
@@ -627,17 +833,56 @@ pub fn parse_block(tokens: Vec<Token>) -> (Expr, usize) {
         TokenType::Else=>{block_kind = BlockType::Else},
         TokenType::ElseIf=>{block_kind = BlockType::ElseIf},
         TokenType::For=>{block_kind = BlockType::For},
-        TokenType::Import=>{block_kind = BlockType::Import; open = true;},
+        TokenType::While=>{block_kind = BlockType::While},
+        TokenType::Loop=>{block_kind = BlockType::Loop},
+        TokenType::Try=>{block_kind = BlockType::Try},
+        TokenType::Catch=>{block_kind = BlockType::Catch},
         TokenType::Return=>{block_kind = BlockType::Return; open = true;},
         _ => {}, 
     }
-    let mut block: Block = Block{kind: block_kind, block: Vec::new(), conditions: Vec::new()};
+    let mut block: Block = Block{kind: block_kind, block: Vec::new(), conditions: Vec::new(), label: None};
     let mut i:usize = 1;
+    match block.kind {
+        BlockType::For => {
+            // `for x in collection` names its loop variables before the `in`
+            match find_in(&tokens) {
+                Some(j) => {
+                    block.kind = BlockType::ForIn;
+                    let mut names: Vec<Box<Expr>> = Vec::new();
+                    for token in &tokens[1..j] {
+                        match token.kind {
+                            TokenType::Name => {
+                                let data = DataType{value: token.value.clone(), kind: Literal::Variable, store: DataStore::new(None, None)};
+                                names.push(Box::new(Expr::Literal(data)));
+                            }
+                            _ => {}
+                        }
+                    }
+                    block.conditions.push(Box::new(Expr::Literal(DataType::array(names))));
+                    i = j + 1;
+                }
+                None => {}
+            }
+        }
+        _ => {}
+    }
     while i < tokens.len() { 
         match tokens[i].kind {
             _ => {
                 if open {
-                    i += parse_any(tokens[i..].to_vec(), &mut block.block, false, true);
+                    match block.kind {
+                        // A bare `return` only takes the rest of its line, a closing brace belongs to the enclosing block
+                        BlockType::Return if !matches!(tokens[i].kind, TokenType::OpeningBrace) => {
+                            i += parse_any(tokens[i..].to_vec(), &mut block.block, false, false);
+                            match tokens.get(i) {
+                                Some(Token{kind: TokenType::ClosingBrace, ..}) => {i -= 1;}
+                                _ => {}
+                            }
+                        }
+                        _ => {
+                            i += parse_any(tokens[i..].to_vec(), &mut block.block, false, true);
+                        }
+                    }
                     break;
                 } else {
                     i += parse_any(tokens[i..].to_vec(), &mut block.conditions, true, true);
@@ -647,12 +892,137 @@ pub fn parse_block(tokens: Vec<Token>) -> (Expr, usize) {
         }
         i += 1;
     }
+    match block.kind {
+        BlockType::If => {return parse_if_chain(tokens, block, i);}
+        BlockType::Try => {return parse_try(tokens, block, i);}
+        _ => {}
+    }
     return (Expr::Block(block), i);
 }
 
+// `try { ... } catch error { ... }`, the catch block is kept in the try's conditions with the name of the error as its label
+pub fn parse_try(tokens: Vec<Token>, mut block: Block, end: usize) -> (Expr, usize) {
+    let mut k = end + 1;
+    while k < tokens.len() {
+        match tokens[k].kind {
+            TokenType::Newline => {k += 1;}
+            _ => {break;}
+        }
+    }
+    match tokens.get(k).map(|token| token.kind) {
+        Some(TokenType::Catch) => {}
+        _ => {return (Expr::Block(block), end);}
+    }
+    let (expr, j) = parse_block(tokens[k..].to_vec());
+    let Expr::Block(mut catch) = expr else { return (Expr::Block(block), end); };
+    match catch.conditions.pop().map(|name| *name) {
+        Some(Expr::Literal(DataType{kind: Literal::Variable, value, ..})) => {catch.label = Some(value);}
+        _ => {}
+    }
+    catch.conditions.clear();
+    block.conditions.push(Box::new(Expr::Block(catch)));
+    return (Expr::Block(block), k + j);
+}
+
+// Collects the `else if` and `else` branches that follow an `if`, blank lines and comments in between are skipped
+pub fn parse_if_chain(tokens: Vec<Token>, first: Block, end: usize) -> (Expr, usize) {
+    let mut chain = Block::new();
+    chain.kind = BlockType::IfChain;
+    chain.block.push(Box::new(Expr::Block(first)));
+    let mut i = end;
+    loop {
+        let mut k = i + 1;
+        while k < tokens.len() {
+            match tokens[k].kind {
+                TokenType::Newline => {k += 1;}
+                _ => {break;}
+            }
+        }
+        if k >= tokens.len() {
+            break;
+        }
+        match tokens[k].kind {
+            TokenType::ElseIf | TokenType::Else => {
+                let (branch, j) = parse_block(tokens[k..].to_vec());
+                chain.block.push(Box::new(branch));
+                i = k + j;
+                match tokens[k].kind {
+                    TokenType::Else => {break;}
+                    _ => {}
+                }
+            }
+            _ => {break;}
+        }
+    }
+    return (Expr::Block(chain), i);
+}
+
+// `match value { pattern | pattern => expr, pattern => { ... } }`, every arm is a MatchArm block holding its patterns as conditions
+pub fn parse_match(tokens: Vec<Token>) -> (Expr, usize) {
+    let mut block = Block::new();
+    block.kind = BlockType::Match;
+    let mut i: usize = 1;
+    i += parse_any(tokens[i..].to_vec(), &mut block.conditions, true, true);
+    i += 1;
+    while i < tokens.len() {
+        match tokens[i].kind {
+            TokenType::Newline | TokenType::Comma | TokenType::Semicolon => {
+                i += 1;
+                continue;
+            }
+            TokenType::ClosingBrace => {break;}
+            _ => {}
+        }
+        let mut arm = Block::new();
+        arm.kind = BlockType::MatchArm;
+        let arrow = find_top_level(&tokens, i, &[TokenType::FatArrow]);
+        while i < arrow {
+            let end = find_top_level(&tokens, i, &[TokenType::Pipe, TokenType::FatArrow]);
+            let mut pattern: Vec<Box<Expr>> = Vec::new();
+            parse_any(tokens[i..end].to_vec(), &mut pattern, false, false);
+            arm.conditions.push(pattern.pop().unwrap_or(Box::new(Expr::Nil)));
+            i = end + 1;
+        }
+        i = arrow + 1;
+        match tokens.get(i).map(|token| token.kind) {
+            Some(TokenType::OpeningBrace) => {
+                i += 1;
+                i += parse_any(tokens[i..].to_vec(), &mut arm.block, false, true);
+                i += 1;
+            }
+            _ => {
+                let end = find_top_level(&tokens, i, &[TokenType::Comma, TokenType::Newline, TokenType::ClosingBrace]);
+                parse_any(tokens[i..end].to_vec(), &mut arm.block, false, false);
+                i = end;
+            }
+        }
+        block.block.push(Box::new(Expr::Block(arm)));
+    }
+    return (Expr::Block(block), i);
+}
+
+// Finds the next token of one of the given kinds that isn't nested inside brackets, or the end of the tokens
+fn find_top_level(tokens: &Vec<Token>, start: usize, kinds: &[TokenType]) -> usize {
+    let mut depth: i32 = 0;
+    for j in start..tokens.len() {
+        if depth == 0 && kinds.contains(&tokens[j].kind) {
+            return j;
+        }
+        match tokens[j].kind {
+            TokenType::OpeningBracket | TokenType::OpeningSquareBracket | TokenType::OpeningBrace => {depth += 1;}
+            TokenType::ClosingBracket | TokenType::ClosingSquareBracket | TokenType::ClosingBrace => {depth -= 1;}
+            _ => {}
+        }
+        if depth < 0 {
+            return j;
+        }
+    }
+    return tokens.len();
+}
+
 
 pub fn parse_function(tokens: Vec<Token>, name: String) -> (Expr, usize) {
-    let mut function_kind: FunctionType = FunctionType::Defined;
+    let function_kind: FunctionType;
     match tokens[0].kind {
         TokenType::Print=>{function_kind = FunctionType::Print},
         TokenType::Length=>{function_kind = FunctionType::Length},
@@ -662,10 +1032,15 @@ pub fn parse_function(tokens: Vec<Token>, name: String) -> (Expr, usize) {
         TokenType::Delete=>{function_kind = FunctionType::Delete},
         TokenType::Int=>{function_kind = FunctionType::Int},
         TokenType::String=>{function_kind = FunctionType::String},
-        _ => {}, 
+        _ => {function_kind = builtin(name.as_str())}, 
     }
     let mut func: Function = Function{kind: function_kind, arguments: Vec::new(), name};
     let mut i:usize = 1;
+    // Keyword builtins still have their opening bracket ahead of the arguments
+    match tokens[0].kind {
+        TokenType::OpeningBracket => {}
+        _ => {i = 2;}
+    }
     i += parse_any(tokens[i..].to_vec(), &mut func.arguments, false, false);
     return (Expr::Function(func), i+1);
 }
@@ -693,12 +1068,19 @@ pub fn parse_definition(tokens: Vec<Token>) -> (Expr, usize) {
                 func.arguments.push(Box::new(expr));
                 i += j;
             }
+            TokenType::Ellipsis => {
+                match tokens.get(i+1) {
+                    Some(Token{kind: TokenType::Name, value}) => {func.rest = Some(value.clone());}
+                    _ => {}
+                }
+                i += 1;
+            }
             _ => {}
         }
         i += 1;
     }
     i += parse_any(tokens[i..].to_vec(), &mut func.block, false, true);
-    return (Expr::Definition(func), i);
+    return (Expr::Definition(Rc::new(func)), i);
 }
 
 pub fn parse_array(tokens: Vec<Token>) -> (Expr, usize) { 
@@ -706,6 +1088,213 @@ pub fn parse_array(tokens: Vec<Token>) -> (Expr, usize) {
     let mut i:usize = 1;
     let mut store: Vec<Box<Expr>> = Vec::new(); 
     i += parse_any(tokens[i..].to_vec(), &mut store, false, false);
-    data.store.array = Some(store);
+    data.store.array = Some(Rc::new(store));
     return (Expr::Literal(data), i);
 }
+
+
+// Only a colon directly between the brackets makes a slice, not one inside of a call, a nested index or a ternary
+fn is_slice(tokens: &Vec<Token>) -> bool {
+    let mut ternaries: usize = 0;
+    let mut j: usize = 1;
+    loop {
+        j = find_top_level(tokens, j, &[TokenType::Question, TokenType::Colon, TokenType::Newline]);
+        match tokens.get(j).map(|token| token.kind) {
+            Some(TokenType::Question) => {ternaries += 1;}
+            Some(TokenType::Colon) => {
+                if ternaries == 0 {
+                    return true;
+                }
+                ternaries -= 1;
+            }
+            _ => {return false;}
+        }
+        j += 1;
+    }
+}
+
+// `target[start:end:step]` becomes a call to the slice builtin, missing bounds are left as Nil
+pub fn parse_slice(tokens: Vec<Token>, target: Expr) -> (Expr, usize) {
+    let mut func: Function = Function{kind: FunctionType::Slice, arguments: vec![Box::new(target)], name: "slice".to_string()};
+    let mut i: usize = 1;
+    while i < tokens.len() {
+        let (bound, j) = parse_expr(tokens[i..].to_vec(), 0);
+        func.arguments.push(Box::new(bound));
+        i += j;
+        match tokens[i].kind {
+            TokenType::Colon => {i += 1;}
+            _ => {break;}
+        }
+    }
+    return (Expr::Function(func), closer_end(&tokens, i, TokenType::ClosingSquareBracket));
+}
+
+fn find_in(tokens: &Vec<Token>) -> Option<usize> {
+    for j in 1..tokens.len() {
+        match tokens[j].kind {
+            TokenType::In => {return Some(j);}
+            TokenType::OpeningBrace | TokenType::Newline | TokenType::Semicolon | TokenType::Equals => {return None;}
+            _ => {}
+        }
+    }
+    return None;
+}
+
+// `let name = value` makes a new variable in the current scope, `global name` makes name refer to the global one
+pub fn parse_declaration(tokens: Vec<Token>) -> (Expr, usize) {
+    let mut block = Block::new();
+    match tokens[0].kind {
+        TokenType::Let => {block.kind = BlockType::Let},
+        TokenType::Global => {block.kind = BlockType::Global},
+        _ => {},
+    }
+    match tokens.get(1) {
+        Some(Token{kind: TokenType::Name, value}) => {
+            let (expr, j) = parse_variable(tokens[2..].to_vec(), value.clone());
+            block.block.push(Box::new(expr));
+            return (Expr::Block(block), j+1);
+        }
+        _ => {}
+    }
+    return (Expr::Block(block), 0);
+}
+
+// `break` and `continue` take an optional loop label, the label is kept in the block
+pub fn parse_jump(tokens: Vec<Token>) -> (Expr, usize) {
+    let mut block = Block::new();
+    match tokens[0].kind {
+        TokenType::Break => {block.kind = BlockType::Break},
+        TokenType::Continue => {block.kind = BlockType::Continue},
+        _ => {},
+    }
+    match tokens.get(1) {
+        Some(Token{kind: TokenType::Name, value}) => {
+            block.label = Some(value.clone());
+            return (Expr::Block(block), 1);
+        }
+        _ => {}
+    }
+    return (Expr::Block(block), 0);
+}
+
+// `import module [as alias]` and `from module import name, name`, modules are std names or quoted paths.
+// The modules go in block, an alias in label and the names picked with `from` in conditions
+pub fn parse_import(tokens: Vec<Token>) -> (Expr, usize) {
+    let mut block = Block::new();
+    block.kind = BlockType::Import;
+    let mut picking = false;
+    let mut i: usize = 1;
+    while i < tokens.len() {
+        match tokens[i].kind {
+            TokenType::Newline | TokenType::Semicolon | TokenType::ClosingBrace => {break;}
+            TokenType::Import => {picking = true;}
+            TokenType::As => {
+                match tokens.get(i+1) {
+                    Some(Token{kind: TokenType::Name, value}) => {
+                        block.label = Some(value.clone());
+                        i += 1;
+                    }
+                    _ => {}
+                }
+            }
+            TokenType::Name => {
+                let data = DataType{value: tokens[i].value.clone(), kind: Literal::Variable, store: DataStore::new(None, None)};
+                if picking {
+                    block.conditions.push(Box::new(Expr::Literal(data)));
+                } else {
+                    block.block.push(Box::new(Expr::Literal(data)));
+                }
+            }
+            TokenType::SingleQuote | TokenType::DoubleQuote => {
+                let (expr, j) = parse_string(tokens[i..].to_vec());
+                block.block.push(Box::new(expr));
+                i += j;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    // The end of the line is left for parse_any, a closing brace still has to close the enclosing block
+    return (Expr::Block(block), i - 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::lexer::tokenize;
+
+    fn parse_source(source: &str) -> Vec<Box<Expr>> {
+        let mut tokens: Vec<Token> = Vec::new();
+        for line in source.lines() {
+            tokens.extend(tokenize(line));
+        }
+        return parse(tokens);
+    }
+
+    // Writes an expression out with every operator grouped, `1 + 2 * 3` is `(Plus 1 (Times 2 3))`
+    fn shape(expr: &Expr) -> String {
+        match expr {
+            Expr::Binary(x) => {return format!("({:?} {} {})", x.operator, shape(&x.left), shape(&x.right));}
+            Expr::Unary(x) => {return format!("({:?} {})", x.operator, shape(&x.value));}
+            Expr::Literal(x) => {return x.value.clone();}
+            _ => {return format!("{:?}", expr);}
+        }
+    }
+
+    fn parsed(source: &str) -> String {
+        let tree = parse_source(source);
+        assert_eq!(tree.len(), 1, "{} should parse to one statement", source);
+        return shape(&tree[0]);
+    }
+
+    #[test]
+    fn times_binds_tighter_than_plus() {
+        assert_eq!(parsed("1 + 2 * 3"), "(Plus 1 (Times 2 3))");
+        assert_eq!(parsed("1 * 2 + 3"), "(Plus (Times 1 2) 3)");
+    }
+
+    #[test]
+    fn minus_is_left_associative() {
+        assert_eq!(parsed("1 - 2 - 3"), "(Minus (Minus 1 2) 3)");
+    }
+
+    #[test]
+    fn power_is_right_associative_and_binds_tighter_than_negation() {
+        assert_eq!(parsed("2 ** 3 ** 2"), "(Power 2 (Power 3 2))");
+        assert_eq!(parsed("-2 ** 2"), "(Negate (Power 2 2))");
+    }
+
+    #[test]
+    fn floor_divide_binds_like_times() {
+        assert_eq!(parsed("1 + 7 ~/ 2"), "(Plus 1 (FloorDivide 7 2))");
+    }
+
+    #[test]
+    fn nested_definitions_keep_the_rest_of_the_body() {
+        let tree = parse_source("fn outer() {\nfn inner() { 1 }\nreturn inner()\n}\nouter()");
+        assert_eq!(tree.len(), 2);
+        match &*tree[0] {
+            Expr::Definition(outer) => {assert_eq!(outer.block.len(), 2);}
+            expr => {panic!("expected a definition, got {:?}", expr);}
+        }
+        let tree = parse_source("if true {\nexport fn f() { 1 }\nprint(2)\n}");
+        match &*tree[0] {
+            Expr::Block(chain) => {
+                let Expr::Block(branch) = &*chain.block[0] else { panic!("expected a branch, got {:?}", chain.block[0]); };
+                assert_eq!(branch.block.len(), 2);
+            }
+            expr => {panic!("expected an if, got {:?}", expr);}
+        }
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(parsed("(1 + 2) * 3"), "(Times (Plus 1 2) 3)");
+    }
+
+    #[test]
+    fn comparisons_bind_looser_than_arithmetic() {
+        assert_eq!(parsed("1 + 2 == 3 && 4 < 5"), "(And (EqualTo (Plus 1 2) 3) (LesserThan 4 5))");
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::parsing::parser::*;
+use crate::runtime::runtime::Flow;
+use crate::std_lib::std_lib::{bindings, exports, locate, read};
+
+// Runs between parsing and running: every named function is registered up front so it can be
+// called above its definition, and calls that can never work are reported before anything runs.
+// Functions defined inside of another one only exist in there, the file only registers its own
+pub fn resolve(tree: &mut [Box<Expr>], functions: &mut HashMap<String, Rc<Definition>>) -> Vec<String> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut imported: HashSet<String> = HashSet::new();
+    // Every imported name with the module it comes from
+    let mut sources: Vec<(String, String)> = Vec::new();
+    for expr in tree.iter() {
+        visit(expr, &mut |expr| {
+            match expr {
+                Expr::Block(import @ Block{kind: BlockType::Import, block, ..}) => {
+                    for lib in block {
+                        let target = lib.expand().unwrap().value;
+                        match locate(&target).and_then(|path| read(&path)).and_then(|module| bindings(import, &target, &exports(&module))) {
+                            Ok(names) => {
+                                for (name, _) in names {
+                                    imported.insert(name.clone());
+                                    sources.push((name, target.clone()));
+                                }
+                            }
+                            Err(Flow::Error(error)) => {errors.push(error);}
+                            Err(_) => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+    let defined: HashSet<String> = names(tree);
+    // An import would replace the script's own function when it runs, so both can't have the same name
+    for (name, target) in &sources {
+        if defined.contains(name) {
+            errors.push(format!("IMPORT ERROR: {} from {} clashes with the {} defined here, import it with `as` or rename one of them", name, target, name));
+        }
+    }
+    // A builtin called by name gives way to a function with the same name from the script or an import.
+    // This happens before the definitions are registered, so they're still the only copy and change in place
+    let mut namespaces: Vec<HashSet<String>> = vec![defined];
+    for expr in tree.iter_mut() {
+        visit_scoped_mut(expr, &mut namespaces, names, &mut |expr, namespaces| {
+            match expr {
+                Expr::Function(call) if named_builtin(call) && (namespaces.iter().any(|names| names.contains(&call.name)) || imported.contains(&call.name)) => {
+                    call.kind = FunctionType::Defined;
+                }
+                _ => {}
+            }
+        });
+    }
+    // The same name can be used again in another function or in the other branch of an if, only
+    // definitions next to each other clash
+    duplicates(tree, &mut errors);
+    let mut bound: HashSet<String> = HashSet::new();
+    for expr in tree.iter() {
+        visit(expr, &mut |expr| {
+            match expr {
+                Expr::Block(block) => {duplicates(&block.block, &mut errors);}
+                Expr::Definition(definition) => {duplicates(&definition.block, &mut errors);}
+                _ => {}
+            }
+            bind(expr, &mut bound);
+        });
+    }
+    for definition in members(tree) {
+        if !functions.contains_key(&definition.name) {
+            functions.insert(definition.name.clone(), definition);
+        }
+    }
+    let mut namespaces: Vec<Vec<Rc<Definition>>> = vec![members(tree)];
+    for expr in tree.iter() {
+        visit_scoped(expr, &mut namespaces, members, &mut |expr, namespaces| {
+            let Expr::Function(call) = expr else { return; };
+            if !matches!(call.kind, FunctionType::Defined) || bound.contains(&call.name) || imported.contains(&call.name) {
+                return;
+            }
+            // The innermost function defining the name is the one that gets called
+            let found = namespaces.iter().rev().find_map(|members| members.iter().find(|definition| definition.name == call.name));
+            match found.or(functions.get(&call.name)) {
+                Some(definition) => {
+                    match check_arguments(call, definition) {
+                        Some(error) => {errors.push(error);}
+                        None => {}
+                    }
+                }
+                None => {errors.push(format!("FUNCTION NOT FOUND: {} wasn't found", call.name));}
+            }
+        });
+    }
+    return errors;
+}
+
+fn duplicates(block: &[Box<Expr>], errors: &mut Vec<String>) {
+    let mut seen: HashSet<&str> = HashSet::new();
+    for expr in block {
+        match &**expr {
+            Expr::Definition(definition) if !definition.name.is_empty() => {
+                if !seen.insert(definition.name.as_str()) {
+                    errors.push(format!("DUPLICATE FUNCTION: {} is defined more than once", definition.name));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The named functions a file or a function body defines, in order. The ones defined inside of those belong to them,
+// but the ones inside of an if or a loop still belong to the enclosing function or file
+pub fn members(block: &[Box<Expr>]) -> Vec<Rc<Definition>> {
+    fn collect(expr: &Expr, members: &mut Vec<Rc<Definition>>) {
+        match expr {
+            Expr::Definition(definition) => {
+                if !definition.name.is_empty() {
+                    members.push(Rc::clone(definition));
+                }
+            }
+            _ => {
+                for child in children(expr) {
+                    collect(child, members);
+                }
+            }
+        }
+    }
+    let mut members: Vec<Rc<Definition>> = Vec::new();
+    for expr in block {
+        collect(expr, &mut members);
+    }
+    return members;
+}
+
+pub fn names(block: &[Box<Expr>]) -> HashSet<String> {
+    return members(block).iter().map(|definition| definition.name.clone()).collect();
+}
+
+// The expressions directly inside of expr
+fn children(expr: &Expr) -> Vec<&Expr> {
+    let mut children: Vec<&Expr> = Vec::new();
+    match expr {
+        Expr::Unary(x) => {children.push(&x.value);}
+        Expr::Binary(x) => {
+            children.push(&x.left);
+            children.push(&x.right);
+        }
+        Expr::Block(x) => {children.extend(x.conditions.iter().chain(x.block.iter()).map(|expr| &**expr));}
+        Expr::Literal(x) => {
+            match (&x.kind, &x.store.array) {
+                (Literal::Array, Some(array)) => {children.extend(array.iter().map(|expr| &**expr));}
+                _ => {}
+            }
+        }
+        Expr::Function(x) => {children.extend(x.arguments.iter().map(|expr| &**expr));}
+        Expr::Definition(x) => {children.extend(x.arguments.iter().chain(x.block.iter()).map(|expr| &**expr));}
+        Expr::Nil => {}
+    }
+    return children;
+}
+
+// Same as children, but shared definitions and arrays are copied first if needed
+fn children_mut(expr: &mut Expr) -> Vec<&mut Expr> {
+    let mut children: Vec<&mut Expr> = Vec::new();
+    match expr {
+        Expr::Unary(x) => {children.push(&mut x.value);}
+        Expr::Binary(x) => {
+            children.push(&mut x.left);
+            children.push(&mut x.right);
+        }
+        Expr::Block(x) => {children.extend(x.conditions.iter_mut().chain(x.block.iter_mut()).map(|expr| &mut **expr));}
+        Expr::Literal(x) => {
+            match (&x.kind, &mut x.store.array) {
+                (Literal::Array, Some(array)) => {children.extend(Rc::make_mut(array).iter_mut().map(|expr| &mut **expr));}
+                _ => {}
+            }
+        }
+        Expr::Function(x) => {children.extend(x.arguments.iter_mut().map(|expr| &mut **expr));}
+        Expr::Definition(x) => {
+            let definition = Rc::make_mut(x);
+            children.extend(definition.arguments.iter_mut().chain(definition.block.iter_mut()).map(|expr| &mut **expr));
+        }
+        Expr::Nil => {}
+    }
+    return children;
+}
+
+// Calls `f` on expr and everything inside of it
+pub fn visit(expr: &Expr, f: &mut dyn FnMut(&Expr)) {
+    f(expr);
+    for child in children(expr) {
+        visit(child, f);
+    }
+}
+
+// Same as visit, but `f` can change what it's given
+pub fn visit_mut(expr: &mut Expr, f: &mut dyn FnMut(&mut Expr)) {
+    f(expr);
+    for child in children_mut(expr) {
+        visit_mut(child, f);
+    }
+}
+
+// Same as visit, but `f` is also given what `open` found in the file and in every function it's inside of, innermost last
+pub fn visit_scoped<N>(expr: &Expr, namespaces: &mut Vec<N>, open: fn(&[Box<Expr>]) -> N, f: &mut dyn FnMut(&Expr, &[N])) {
+    f(expr, namespaces);
+    let opened = match expr {
+        Expr::Definition(definition) => {
+            namespaces.push(open(&definition.block));
+            true
+        }
+        _ => false,
+    };
+    for child in children(expr) {
+        visit_scoped(child, namespaces, open, f);
+    }
+    if opened {
+        namespaces.pop();
+    }
+}
+
+pub fn visit_scoped_mut<N>(expr: &mut Expr, namespaces: &mut Vec<N>, open: fn(&[Box<Expr>]) -> N, f: &mut dyn FnMut(&mut Expr, &[N])) {
+    f(expr, namespaces);
+    let opened = match expr {
+        Expr::Definition(definition) => {
+            namespaces.push(open(&definition.block));
+            true
+        }
+        _ => false,
+    };
+    for child in children_mut(expr) {
+        visit_scoped_mut(child, namespaces, open, f);
+    }
+    if opened {
+        namespaces.pop();
+    }
+}
+
+// Names that get a value somewhere, any of them could be holding a function by the time it's called
+pub fn bind(expr: &Expr, bound: &mut HashSet<String>) {
+    match expr {
+        Expr::Binary(BinaryExpr{operator: Operator::Equals, left, ..}) => {
+            match left.expand() {
+                Some(name) => {bound.insert(name.value);}
+                None => {}
+            }
+        }
+        Expr::Definition(definition) => {
+            for parameter in &definition.arguments {
+                match parameter.expand() {
+                    Some(name) => {bound.insert(name.value);}
+                    None => {}
+                }
+            }
+            match &definition.rest {
+                Some(name) => {bound.insert(name.clone());}
+                None => {}
+            }
+        }
+        Expr::Block(Block{kind: BlockType::Let | BlockType::Global, block, ..}) => {
+            match block.first().and_then(|name| name.expand()) {
+                Some(name) => {bound.insert(name.value);}
+                None => {}
+            }
+        }
+        Expr::Block(Block{kind: BlockType::Catch, label: Some(name), ..}) => {
+            bound.insert(name.clone());
+        }
+        Expr::Block(Block{kind: BlockType::ForIn | BlockType::MatchArm, conditions, ..}) => {
+            for condition in conditions {
+                visit(condition, &mut |expr| {
+                    match expr {
+                        Expr::Literal(DataType{kind: Literal::Variable, value, ..}) => {bound.insert(value.clone());}
+                        _ => {}
+                    }
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_arguments(call: &Function, definition: &Definition) -> Option<String> {
+    let mut required: Vec<String> = Vec::new();
+    let mut parameters: Vec<String> = Vec::new();
+    for parameter in &definition.arguments {
+        match &**parameter {
+            Expr::Binary(BinaryExpr{left, ..}) => {parameters.push(left.expand().unwrap().value);}
+            _ => {
+                let name = parameter.expand().unwrap().value;
+                required.push(name.clone());
+                parameters.push(name);
+            }
+        }
+    }
+    let mut named: Vec<String> = Vec::new();
+    let mut positional: usize = 0;
+    for argument in &call.arguments {
+        match &**argument {
+            Expr::Binary(BinaryExpr{operator: Operator::Equals, left, ..}) => {
+                let name = left.expand().unwrap().value;
+                if !parameters.contains(&name) {
+                    return Some(format!("INVALID ARGUMENTS: {} has no parameter called {}", short_name(&call.name), name));
+                }
+                named.push(name);
+            }
+            _ => {positional += 1;}
+        }
+    }
+    if positional + named.len() > parameters.len() && definition.rest.is_none() {
+        return Some(format!("INVALID ARGUMENTS: {} takes at most {} arguments but was given {}", short_name(&call.name), parameters.len(), positional + named.len()));
+    }
+    // Positional arguments fill the parameters that weren't named, in order
+    let mut open = parameters.iter().filter(|parameter| !named.contains(parameter));
+    for _ in 0..positional {
+        open.next();
+    }
+    for parameter in open {
+        if required.contains(parameter) {
+            return Some(format!("INVALID ARGUMENTS: {} is missing a value for {}", short_name(&call.name), parameter));
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::lexer::{Token, tokenize};
+
+    fn resolved(source: &str) -> (Vec<Box<Expr>>, HashMap<String, Rc<Definition>>, Vec<String>) {
+        let mut tokens: Vec<Token> = Vec::new();
+        for line in source.lines() {
+            tokens.extend(tokenize(line));
+        }
+        let mut tree = parse(tokens);
+        let mut functions: HashMap<String, Rc<Definition>> = HashMap::new();
+        let errors = resolve(&mut tree, &mut functions);
+        return (tree, functions, errors);
+    }
+
+    #[test]
+    fn functions_can_be_called_above_their_definition() {
+        let (_, functions, errors) = resolved("print(square(4))\nfn square(x) { x * x }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(functions.contains_key("square"));
+    }
+
+    #[test]
+    fn nested_definitions_stay_inside_their_function() {
+        let (tree, functions, errors) = resolved("fn outer() {\nfn inner() { 1 }\nreturn inner()\n}\nouter()");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(tree.len(), 2);
+        match &*tree[0] {
+            Expr::Definition(outer) => {
+                assert_eq!(outer.block.len(), 2);
+                assert!(matches!(&*outer.block[0], Expr::Definition(inner) if inner.name == "inner"));
+            }
+            expr => {panic!("expected a definition, got {:?}", expr);}
+        }
+        assert!(functions.contains_key("outer"));
+        assert!(!functions.contains_key("inner"));
+        let (_, _, errors) = resolved("fn outer() {\nfn inner() { 1 }\n}\ninner()");
+        assert_eq!(errors, vec!["FUNCTION NOT FOUND: inner wasn't found".to_string()]);
+    }
+
+    #[test]
+    fn functions_in_both_branches_of_an_if_are_not_duplicates() {
+        let (_, functions, errors) = resolved("if true {\nfn greet() { \"yes\" }\n} else {\nfn greet() { \"no\" }\n}\ngreet()");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(functions.contains_key("greet"));
+    }
+
+    #[test]
+    fn inner_functions_are_checked_against_their_own_namespace() {
+        let (_, _, errors) = resolved("fn a() {\nfn helper() { 1 }\nreturn helper()\n}\nfn b() {\nfn helper(x) { x }\nreturn helper(2)\n}");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (_, _, errors) = resolved("fn a() {\nfn helper() { 1 }\nfn helper() { 2 }\n}");
+        assert_eq!(errors, vec!["DUPLICATE FUNCTION: helper is defined more than once".to_string()]);
+    }
+
+    #[test]
+    fn unknown_functions_are_reported() {
+        let (_, _, errors) = resolved("missing(1)");
+        assert_eq!(errors, vec!["FUNCTION NOT FOUND: missing wasn't found".to_string()]);
+    }
+
+    #[test]
+    fn calls_through_variables_are_left_to_run_time() {
+        let (_, _, errors) = resolved("f = fn(x) { x }\nf(1, 2, 3)");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn duplicate_definitions_are_reported() {
+        let (_, _, errors) = resolved("fn twice(x) { x }\nfn twice(x) { x * 2 }");
+        assert_eq!(errors, vec!["DUPLICATE FUNCTION: twice is defined more than once".to_string()]);
+    }
+
+    #[test]
+    fn arity_is_checked_before_running() {
+        let (_, _, errors) = resolved("fn add(a, b) { a + b }\nadd(1, 2, 3)");
+        assert_eq!(errors, vec!["INVALID ARGUMENTS: add takes at most 2 arguments but was given 3".to_string()]);
+        let (_, _, errors) = resolved("fn add(a, b) { a + b }\nadd(1)");
+        assert_eq!(errors, vec!["INVALID ARGUMENTS: add is missing a value for b".to_string()]);
+        let (_, _, errors) = resolved("fn add(a, b) { a + b }\nadd(1, c = 2)");
+        assert_eq!(errors, vec!["INVALID ARGUMENTS: add has no parameter called c".to_string()]);
+    }
+
+    #[test]
+    fn defaults_and_rest_parameters_are_optional() {
+        let (_, _, errors) = resolved("fn greet(name, greeting = \"hi\", ...others) { name }\ngreet(\"bob\")\ngreet(\"bob\", \"hey\", \"eve\", \"al\")");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn script_functions_replace_builtins_with_the_same_name() {
+        let (tree, _, errors) = resolved("fn upper(x) { x }\nupper(\"abc\")");
+        assert!(errors.is_empty(), "{:?}", errors);
+        match &*tree[1] {
+            Expr::Function(call) => {assert!(matches!(call.kind, FunctionType::Defined));}
+            expr => {panic!("expected a call, got {:?}", expr);}
+        }
+    }
+}
@@ -1,96 +1,580 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
 use crate::parsing::parser::{*};
 use crate::runtime::runtime::*;
+use crate::runtime::operations::{as_float, compare, equals, resolve_index, slice, truthy};
 use crate::HashMap;
 
 
-pub fn run_print(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) {
+pub fn run_print(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(), Flow> {
     for arg in &expr.arguments {
-        let output = calculate_bexpr(&arg, scopes, functions).unwrap(); 
+        let output = calculate_bexpr(&arg, scopes, functions)?.unwrap_or(DataType::new()); 
         match output.kind {
             Literal::Array => {
                 print!("[ ");
-                for value in output.store.array.unwrap() {
-                   print!("{}; ", calculate_bexpr(&value, scopes, functions).unwrap().value) 
+                for value in output.store.array.unwrap().iter() {
+                    let element = calculate_bexpr(&value, scopes, functions)?.unwrap_or(DataType::new());
+                    // Nested arrays have no value of their own
+                    match element.kind {
+                        Literal::Array => {print!("{}; ", display(&element));}
+                        _ => {print!("{}; ", element.value);}
+                    }
                 }
                 print!("]\n");
             }
             _ => { print!("{}\n", output.value) }
         }
     }
+    return Ok(());
 }
 
-pub fn run_len(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    for arg in &expr.arguments {
-        let output: i32 = calculate_bexpr(&arg, scopes, functions).unwrap().store.array.unwrap().len().try_into().unwrap(); 
-        return Some(DataType{value: output.to_string(), kind: Literal::Int, store: DataStore::new(Some(output), None)});
+pub fn run_len(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = value_argument(expr, 0, "len", scopes, functions)?;
+    let output: usize;
+    match (&data.kind, &data.store.array) {
+        (Literal::String, _) => {output = data.value.chars().count();}
+        (Literal::Array, Some(store)) => {output = store.len();}
+        _ => {return Err(Flow::Error(format!("TYPE ERROR: len needs a string or an array, not {}", type_name(&data.kind))));}
     }
-    return None;
+    return Ok(Some(DataType::int(output as i32)));
 }
 
 
-pub fn run_pop(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    for arg in &expr.arguments {
-        let mut array = calculate_bexpr(&arg, scopes, functions).unwrap(); 
-        let mut store = array.store.array.unwrap().clone();
-        store.pop();
-        array.store.array = Some(store);
-        return Some(array);
-    }
-    return None;
+pub fn run_pop(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let (mut array, mut store) = array_store(expr, 0, "pop", scopes, functions)?;
+    store.pop();
+    array.store.array = Some(Rc::new(store));
+    return Ok(Some(array));
 }
 
 
-pub fn run_push(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    let mut array: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions).unwrap();
-    let mut store = array.store.array.unwrap();
+pub fn run_push(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let (mut array, mut store) = array_store(expr, 0, "push", scopes, functions)?;
     for i in 1..expr.arguments.len() {
-        let arg = calculate_bexpr(&expr.arguments[i], scopes, functions).unwrap();
+        let arg = value_argument(expr, i, "push", scopes, functions)?;
         store.push(Box::new(Expr::Literal(arg)));
     }
-    array.store.array = Some(store);
-    return Some(array);
+    array.store.array = Some(Rc::new(store));
+    return Ok(Some(array));
 }
 
-pub fn run_swap(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    let mut array: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions).unwrap();
-    let mut store = array.store.array.unwrap();
-    let mut index_int = calculate_bexpr(&expr.arguments[1], scopes, functions).unwrap().store.integer.unwrap();
-    if index_int < 0 {
-        let length: i32 = store.len().try_into().unwrap(); 
-        index_int = length + index_int; 
-    }
-    let index: usize = index_int.try_into().unwrap();
-    let value = calculate_bexpr(&expr.arguments[2], scopes, functions).unwrap();
+pub fn run_swap(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let (mut array, mut store) = array_store(expr, 0, "swap", scopes, functions)?;
+    let index = resolve_index(value_argument(expr, 1, "swap", scopes, functions)?, store.len())?;
+    let value = value_argument(expr, 2, "swap", scopes, functions)?;
     store[index] = Box::new(Expr::Literal(value));
-    array.store.array = Some(store);
-    return Some(array);
+    array.store.array = Some(Rc::new(store));
+    return Ok(Some(array));
 }
 
 
-pub fn run_delete(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    let mut array: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions).unwrap();
-    let mut store = array.store.array.unwrap();
-    let mut index_int = calculate_bexpr(&expr.arguments[1], scopes, functions).unwrap().store.integer.unwrap();
-    if index_int < 0 {
-        let length: i32 = store.len().try_into().unwrap(); 
-        index_int = length + index_int; 
-    }
-    let index: usize = index_int.try_into().unwrap();
+pub fn run_delete(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let (mut array, mut store) = array_store(expr, 0, "delete", scopes, functions)?;
+    let index = resolve_index(value_argument(expr, 1, "delete", scopes, functions)?, store.len())?;
     store.remove(index);
-    array.store.array = Some(store);
-    return Some(array);
+    array.store.array = Some(Rc::new(store));
+    return Ok(Some(array));
+}
+
+// The array argument at `index` with its elements taken out to be changed, they're only copied when something else still shares them
+fn array_store(expr: &Function, index: usize, name: &str, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(DataType, Vec<Box<Expr>>), Flow> {
+    if expr.arguments.len() <= index {
+        return Err(Flow::Error(format!("INVALID ARGUMENTS: {} is missing an array", name)));
+    }
+    let mut data: DataType = calculate_bexpr(&expr.arguments[index], scopes, functions)?.unwrap_or(DataType::new());
+    match data.store.array.take() {
+        Some(store) if matches!(data.kind, Literal::Array) => {return Ok((data, Rc::unwrap_or_clone(store)));}
+        _ => {return Err(Flow::Error(format!("TYPE ERROR: {} needs an array, not {}", name, type_name(&data.kind))));}
+    }
+}
+
+// The elements of the array argument at `index`, `name` is the builtin for the error message
+fn array_argument(expr: &Function, index: usize, name: &str, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Vec<DataType>, Flow> {
+    if expr.arguments.len() <= index {
+        return Err(Flow::Error(format!("INVALID ARGUMENTS: {} is missing an array", name)));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[index], scopes, functions)?.unwrap_or(DataType::new());
+    match data.store.array {
+        Some(store) if matches!(data.kind, Literal::Array) => {
+            let mut values: Vec<DataType> = Vec::new();
+            for value in store.iter() {
+                values.push(value.expand().unwrap_or(DataType::new()));
+            }
+            return Ok(values);
+        }
+        _ => {return Err(Flow::Error(format!("TYPE ERROR: {} needs an array, not {}", name, type_name(&data.kind))));}
+    }
+}
+
+// The argument at `index`, it has to be there
+fn value_argument(expr: &Function, index: usize, name: &str, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<DataType, Flow> {
+    if expr.arguments.len() <= index {
+        return Err(Flow::Error(format!("INVALID ARGUMENTS: {} is missing a value", name)));
+    }
+    return Ok(calculate_bexpr(&expr.arguments[index], scopes, functions)?.unwrap_or(DataType::new()));
+}
+
+fn string_argument(expr: &Function, index: usize, name: &str, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<String, Flow> {
+    if expr.arguments.len() <= index {
+        return Err(Flow::Error(format!("INVALID ARGUMENTS: {} is missing a string", name)));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[index], scopes, functions)?.unwrap_or(DataType::new());
+    match data.kind {
+        Literal::String => {return Ok(data.value);}
+        _ => {return Err(Flow::Error(format!("TYPE ERROR: {} needs a string, not {}", name, type_name(&data.kind))));}
+    }
+}
+
+fn function_argument(expr: &Function, index: usize, name: &str, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<DataType, Flow> {
+    if expr.arguments.len() <= index {
+        return Err(Flow::Error(format!("INVALID ARGUMENTS: {} is missing a function", name)));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[index], scopes, functions)?.unwrap_or(DataType::new());
+    match data.kind {
+        Literal::Function => {return Ok(data);}
+        _ => {return Err(Flow::Error(format!("TYPE ERROR: {} needs a function, not {}", name, type_name(&data.kind))));}
+    }
+}
+
+// Calls a function value with already evaluated arguments
+fn call_with(function: &DataType, values: Vec<DataType>, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<DataType, Flow> {
+    let mut arguments: Vec<Box<Expr>> = Vec::new();
+    for value in values {
+        arguments.push(Box::new(Expr::Literal(value)));
+    }
+    let closure = function.store.function.clone().unwrap();
+    return Ok(call_closure(&closure, &arguments, scopes, functions)?.unwrap_or(DataType::new()));
+}
+
+fn array_of(values: Vec<DataType>) -> DataType {
+    let mut store: Vec<Box<Expr>> = Vec::new();
+    for value in values {
+        store.push(Box::new(Expr::Literal(value)));
+    }
+    return DataType::array(store);
+}
+
+pub fn run_map(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "map", scopes, functions)?;
+    let function = function_argument(expr, 1, "map", scopes, functions)?;
+    let mut output: Vec<DataType> = Vec::new();
+    for value in values {
+        output.push(call_with(&function, vec![value], scopes, functions)?);
+    }
+    return Ok(Some(array_of(output)));
+}
+
+pub fn run_filter(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "filter", scopes, functions)?;
+    let function = function_argument(expr, 1, "filter", scopes, functions)?;
+    let mut output: Vec<DataType> = Vec::new();
+    for value in values {
+        if truthy(&call_with(&function, vec![value.clone()], scopes, functions)?) {
+            output.push(value);
+        }
+    }
+    return Ok(Some(array_of(output)));
+}
+
+// `reduce(arr, f, init)`, without init the first element is where it starts
+pub fn run_reduce(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let mut values = array_argument(expr, 0, "reduce", scopes, functions)?;
+    let function = function_argument(expr, 1, "reduce", scopes, functions)?;
+    let mut output: DataType;
+    if expr.arguments.len() > 2 {
+        output = calculate_bexpr(&expr.arguments[2], scopes, functions)?.unwrap_or(DataType::new());
+    } else if values.is_empty() {
+        return Err(Flow::Error("INVALID ARGUMENTS: reduce of an empty array needs a starting value".to_string()));
+    } else {
+        output = values.remove(0);
+    }
+    for value in values {
+        output = call_with(&function, vec![output, value], scopes, functions)?;
+    }
+    return Ok(Some(output));
+}
+
+fn sort_values(values: Vec<DataType>, keys: Vec<DataType>) -> Result<Vec<DataType>, Flow> {
+    let mut pairs: Vec<(DataType, DataType)> = keys.into_iter().zip(values).collect();
+    let mut error: Option<Flow> = None;
+    pairs.sort_by(|(left, _), (right, _)| {
+        match compare(left, right) {
+            Some(ordering) => {return ordering;}
+            None => {
+                if error.is_none() {
+                    error = Some(Flow::Error(format!("TYPE ERROR: {} and {} can't be sorted together", type_name(&left.kind), type_name(&right.kind))));
+                }
+                return Ordering::Equal;
+            }
+        }
+    });
+    match error {
+        Some(error) => {return Err(error);}
+        None => {return Ok(pairs.into_iter().map(|(_, value)| value).collect());}
+    }
+}
+
+pub fn run_sort(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "sort", scopes, functions)?;
+    return Ok(Some(array_of(sort_values(values.clone(), values)?)));
+}
+
+// `sort_by(arr, f)` sorts by what f gives back for each element, keeping the order of equal ones
+pub fn run_sort_by(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "sort_by", scopes, functions)?;
+    let function = function_argument(expr, 1, "sort_by", scopes, functions)?;
+    let mut keys: Vec<DataType> = Vec::new();
+    for value in &values {
+        keys.push(call_with(&function, vec![value.clone()], scopes, functions)?);
+    }
+    return Ok(Some(array_of(sort_values(values, keys)?)));
+}
+
+pub fn run_find(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "find", scopes, functions)?;
+    let function = function_argument(expr, 1, "find", scopes, functions)?;
+    for value in values {
+        if truthy(&call_with(&function, vec![value.clone()], scopes, functions)?) {
+            return Ok(Some(value));
+        }
+    }
+    return Ok(Some(DataType::new()));
+}
+
+pub fn run_any(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "any", scopes, functions)?;
+    let function = function_argument(expr, 1, "any", scopes, functions)?;
+    for value in values {
+        if truthy(&call_with(&function, vec![value], scopes, functions)?) {
+            return Ok(Some(DataType::bool(true)));
+        }
+    }
+    return Ok(Some(DataType::bool(false)));
+}
+
+pub fn run_all(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "all", scopes, functions)?;
+    let function = function_argument(expr, 1, "all", scopes, functions)?;
+    for value in values {
+        if !truthy(&call_with(&function, vec![value], scopes, functions)?) {
+            return Ok(Some(DataType::bool(false)));
+        }
+    }
+    return Ok(Some(DataType::bool(true)));
+}
+
+// Pairs up elements by position and stops at the end of the shorter array
+pub fn run_zip(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let left = array_argument(expr, 0, "zip", scopes, functions)?;
+    let right = array_argument(expr, 1, "zip", scopes, functions)?;
+    let mut output: Vec<DataType> = Vec::new();
+    for (x, y) in left.into_iter().zip(right) {
+        output.push(array_of(vec![x, y]));
+    }
+    return Ok(Some(array_of(output)));
+}
+
+pub fn run_enumerate(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "enumerate", scopes, functions)?;
+    let mut output: Vec<DataType> = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        output.push(array_of(vec![DataType::int(i as i32), value]));
+    }
+    return Ok(Some(array_of(output)));
+}
+
+pub fn run_reverse(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = value_argument(expr, 0, "reverse", scopes, functions)?;
+    match data.kind {
+        Literal::String => {return Ok(Some(DataType::string(data.value.chars().rev().collect())));}
+        _ => {}
+    }
+    let mut values = array_argument(expr, 0, "reverse", scopes, functions)?;
+    values.reverse();
+    return Ok(Some(array_of(values)));
+}
+
+// Keeps the first of every group of equal elements
+pub fn run_unique(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "unique", scopes, functions)?;
+    let mut output: Vec<DataType> = Vec::new();
+    for value in values {
+        if !output.iter().any(|seen| equals(seen.clone(), value.clone()).and_then(|z| z.store.bool).unwrap_or(false)) {
+            output.push(value);
+        }
+    }
+    return Ok(Some(array_of(output)));
 }
 
-pub fn run_int(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    let mut data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions).unwrap();
-    data.kind = Literal::Int;
-    data.store.integer = Some(data.value.parse().expect("CONVERSION ERROR: This value was not able to be converted into an integer"));
-    return Some(data);
+fn conversion_error(data: &DataType, into: &str) -> Flow {
+    return Flow::Error(format!("CONVERSION ERROR: {} ({}) can't be converted into {}", data.value, type_name(&data.kind), into));
 }
 
-pub fn run_string(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    let mut data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions).unwrap();
-    data.kind = Literal::String;
-    data.store.integer = None;
-    return Some(data);
+// `int(x)` or `int(x, base)`, a 0x, 0o or 0b prefix matching the base is allowed
+pub fn run_int(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.is_empty() || expr.arguments.len() > 2 {
+        return Err(Flow::Error("INVALID ARGUMENTS: int takes a value and optionally a base".to_string()));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+    let mut base: u32 = 10;
+    if expr.arguments.len() > 1 {
+        let given = calculate_bexpr(&expr.arguments[1], scopes, functions)?.unwrap_or(DataType::new());
+        match given.store.integer {
+            Some(2..=36) => {base = given.store.integer.unwrap() as u32;}
+            _ => {return Err(Flow::Error(format!("CONVERSION ERROR: {} isn't a base between 2 and 36", given.value)));}
+        }
+    }
+    match data.kind {
+        Literal::Int => {return Ok(Some(data));}
+        Literal::Float => {
+            let float = data.store.float.unwrap().trunc();
+            if !float.is_finite() || float < i32::MIN as f64 || float > i32::MAX as f64 {
+                return Err(conversion_error(&data, "an int"));
+            }
+            return Ok(Some(DataType::int(float as i32)));
+        }
+        Literal::Bool => {return Ok(Some(DataType::int(data.store.bool.unwrap() as i32)));}
+        Literal::String => {
+            let text = data.value.trim();
+            let (negative, digits) = match text.strip_prefix("-") {
+                Some(rest) => (true, rest),
+                None => (false, text),
+            };
+            let prefix = match base {
+                16 => "0x",
+                8 => "0o",
+                2 => "0b",
+                _ => "",
+            };
+            let digits = digits.strip_prefix(prefix).unwrap_or(digits);
+            // The sign goes back on before parsing so i32::MIN still fits
+            let signed = if negative { format!("-{}", digits) } else { digits.to_string() };
+            match i32::from_str_radix(signed.as_str(), base) {
+                Ok(integer) => {return Ok(Some(DataType::int(integer)));}
+                Err(_) => {return Err(conversion_error(&data, "an int"));}
+            }
+        }
+        _ => {return Err(conversion_error(&data, "an int"));}
+    }
+}
+
+pub fn run_float(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() != 1 {
+        return Err(Flow::Error("INVALID ARGUMENTS: float takes exactly one value".to_string()));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+    match data.kind {
+        Literal::Int | Literal::Float => {return Ok(Some(DataType::float(as_float(&data).unwrap())));}
+        Literal::Bool => {return Ok(Some(DataType::float(data.store.bool.unwrap() as i32 as f64)));}
+        Literal::String => {
+            match data.value.trim().parse::<f64>() {
+                Ok(float) => {return Ok(Some(DataType::float(float)));}
+                Err(_) => {return Err(conversion_error(&data, "a float"));}
+            }
+        }
+        _ => {return Err(conversion_error(&data, "a float"));}
+    }
+}
+
+pub fn run_string(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() != 1 {
+        return Err(Flow::Error("INVALID ARGUMENTS: string takes exactly one value".to_string()));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+    return Ok(Some(DataType::string(display(&data))));
+}
+
+// Strings become an array of their characters, nil an empty array and any other single value a one element array
+pub fn run_array(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() != 1 {
+        return Err(Flow::Error("INVALID ARGUMENTS: array takes exactly one value".to_string()));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+    let mut store: Vec<Box<Expr>> = Vec::new();
+    match data.kind {
+        Literal::Array => {return Ok(Some(data));}
+        Literal::Nil => {}
+        Literal::String => {
+            for c in data.value.chars() {
+                store.push(Box::new(Expr::Literal(DataType::string(c.to_string()))));
+            }
+        }
+        _ => {store.push(Box::new(Expr::Literal(data)));}
+    }
+    return Ok(Some(DataType::array(store)));
+}
+
+pub fn run_type(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() != 1 {
+        return Err(Flow::Error("INVALID ARGUMENTS: type takes exactly one value".to_string()));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+    return Ok(Some(DataType::string(type_name(&data.kind).to_string())));
+}
+
+pub fn type_name(kind: &Literal) -> &'static str {
+    match kind {
+        Literal::String => {return "string";}
+        Literal::Int => {return "int";}
+        Literal::Float => {return "float";}
+        Literal::Bool => {return "bool";}
+        Literal::Array => {return "array";}
+        Literal::Function => {return "function";}
+        Literal::Variable | Literal::Nil => {return "nil";}
+    }
+}
+
+// How a value reads as a string, arrays show their elements with strings quoted
+pub fn display(data: &DataType) -> String {
+    match data.kind {
+        Literal::Array => {
+            let mut parts: Vec<String> = Vec::new();
+            for value in data.store.array.clone().unwrap_or_default().iter() {
+                let element = value.expand().unwrap_or(DataType::new());
+                match element.kind {
+                    Literal::String => {parts.push(format!("{:?}", element.value));}
+                    _ => {parts.push(display(&element));}
+                }
+            }
+            return format!("[{}]", parts.join(", "));
+        }
+        _ => {return data.value.clone();}
+    }
+}
+
+pub fn run_slice(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() > 4 {
+        return Err(Flow::Error("SLICE ERROR: A slice takes at most a start, an end and a step".to_string()));
+    }
+    let data: DataType = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap();
+    let mut bounds: Vec<Option<DataType>> = Vec::new();
+    for bound in &expr.arguments[1..] {
+        bounds.push(calculate_bexpr(&bound, scopes, functions)?);
+    }
+    bounds.resize(3, None);
+    return Ok(slice(data, bounds[0].clone(), bounds[1].clone(), bounds[2].clone())?);
+}
+
+pub fn run_split(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "split", scopes, functions)?;
+    let mut store: Vec<Box<Expr>> = Vec::new();
+    if expr.arguments.len() < 2 {
+        for part in data.split_whitespace() {
+            store.push(Box::new(Expr::Literal(DataType::string(part.to_string()))));
+        }
+        return Ok(Some(DataType::array(store)));
+    }
+    let separator = string_argument(expr, 1, "split", scopes, functions)?;
+    for part in data.split(separator.as_str()) {
+        store.push(Box::new(Expr::Literal(DataType::string(part.to_string()))));
+    }
+    return Ok(Some(DataType::array(store)));
+}
+
+pub fn run_join(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = array_argument(expr, 0, "join", scopes, functions)?;
+    let mut separator = "".to_string();
+    if expr.arguments.len() > 1 {
+        separator = string_argument(expr, 1, "join", scopes, functions)?;
+    }
+    let mut parts: Vec<String> = Vec::new();
+    for value in values {
+        parts.push(display(&value));
+    }
+    return Ok(Some(DataType::string(parts.join(separator.as_str()))));
+}
+
+pub fn run_trim(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "trim", scopes, functions)?;
+    return Ok(Some(DataType::string(data.trim().to_string())));
+}
+
+pub fn run_replace(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "replace", scopes, functions)?;
+    let from = string_argument(expr, 1, "replace", scopes, functions)?;
+    let to = string_argument(expr, 2, "replace", scopes, functions)?;
+    return Ok(Some(DataType::string(data.replace(from.as_str(), to.as_str()))));
+}
+
+pub fn run_contains(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = value_argument(expr, 0, "contains", scopes, functions)?;
+    let needle = value_argument(expr, 1, "contains", scopes, functions)?;
+    match data.kind {
+        Literal::Array => {
+            for element in array_argument(expr, 0, "contains", scopes, functions)? {
+                match equals(element, needle.clone()) {
+                    Some(found) => {
+                        if found.store.bool.unwrap() {
+                            return Ok(Some(DataType::bool(true)));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(Some(DataType::bool(false)));
+        }
+        Literal::String => {
+            let needle = string_argument(expr, 1, "contains", scopes, functions)?;
+            return Ok(Some(DataType::bool(data.value.contains(needle.as_str()))));
+        }
+        _ => {return Err(Flow::Error(format!("TYPE ERROR: contains needs a string or an array, not {}", type_name(&data.kind))));}
+    }
+}
+
+pub fn run_starts_with(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "starts_with", scopes, functions)?;
+    let prefix = string_argument(expr, 1, "starts_with", scopes, functions)?;
+    return Ok(Some(DataType::bool(data.starts_with(prefix.as_str()))));
+}
+
+pub fn run_ends_with(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "ends_with", scopes, functions)?;
+    let suffix = string_argument(expr, 1, "ends_with", scopes, functions)?;
+    return Ok(Some(DataType::bool(data.ends_with(suffix.as_str()))));
+}
+
+pub fn run_upper(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "upper", scopes, functions)?;
+    return Ok(Some(DataType::string(data.to_uppercase())));
+}
+
+pub fn run_bool(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() != 1 {
+        return Err(Flow::Error("INVALID ARGUMENTS: bool takes exactly one value".to_string()));
+    }
+    return Ok(Some(DataType::bool(run_condition(&expr.arguments[0], scopes, functions)?)));
+}
+
+pub fn run_is_nil(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if expr.arguments.len() != 1 {
+        return Err(Flow::Error("INVALID ARGUMENTS: is_nil takes exactly one value".to_string()));
+    }
+    let data = calculate_bexpr(&expr.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+    return Ok(Some(DataType::bool(matches!(data.kind, Literal::Nil))));
+}
+
+pub fn run_lower(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "lower", scopes, functions)?;
+    return Ok(Some(DataType::string(data.to_lowercase())));
+}
+
+pub fn run_lines(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let data = string_argument(expr, 0, "lines", scopes, functions)?;
+    let mut store: Vec<Box<Expr>> = Vec::new();
+    for line in data.lines() {
+        store.push(Box::new(Expr::Literal(DataType::string(line.to_string()))));
+    }
+    return Ok(Some(DataType::array(store)));
+}
+
+pub fn run_exit(expr: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Flow {
+    if expr.arguments.len() == 0 {
+        return Flow::Exit(0);
+    }
+    match calculate_bexpr(&expr.arguments[0], scopes, functions) {
+        Ok(Some(DataType{kind: Literal::Int, store, ..})) => {return Flow::Exit(store.integer.unwrap());}
+        Ok(..) => {return Flow::Error("EXIT ERROR: Exit codes have to be integers".to_string());}
+        Err(flow) => {return flow;}
+    }
 }
@@ -1,9 +1,44 @@
+use std::cmp::Ordering;
+
 use crate::parsing::parser::{*};
 
+// Ints mixed with floats are worked out as floats
+fn floats(left: &DataType, right: &DataType) -> Option<(f64, f64)> {
+    match (left.kind, right.kind) {
+        (Literal::Float, Literal::Float) | (Literal::Float, Literal::Int) | (Literal::Int, Literal::Float) => {
+            return Some((as_float(left)?, as_float(right)?));
+        }
+        _ => {return None;}
+    }
+}
+
+// How two values sort against each other, None when they can't be compared
+pub fn compare(left: &DataType, right: &DataType) -> Option<Ordering> {
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {return Some(left.store.integer?.cmp(&right.store.integer?));}
+        (Literal::Int | Literal::Float, Literal::Int | Literal::Float) => {return as_float(left)?.partial_cmp(&as_float(right)?);}
+        (Literal::String, Literal::String) => {return Some(left.value.cmp(&right.value));}
+        (Literal::Bool, Literal::Bool) => {return Some(left.store.bool?.cmp(&right.store.bool?));}
+        _ => {return None;}
+    }
+}
+
+pub fn as_float(data: &DataType) -> Option<f64> {
+    match data.kind {
+        Literal::Int => {return Some(data.store.integer? as f64);}
+        Literal::Float => {return data.store.float;}
+        _ => {return None;}
+    }
+}
+
 pub fn add(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float(x + y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
-            let z:i32 = left.store.integer.unwrap() + right.store.integer.unwrap();
+            let z:i32 = left.store.integer.unwrap().checked_add(right.store.integer.unwrap())?;
             return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
         }
         (Literal::String, Literal::String) => {
@@ -16,9 +51,13 @@ pub fn add(left: DataType, right: DataType) -> Option<DataType> {
 
 
 pub fn subtract(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float(x - y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
-            let z:i32 = left.store.integer.unwrap() - right.store.integer.unwrap();
+            let z:i32 = left.store.integer.unwrap().checked_sub(right.store.integer.unwrap())?;
             return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
         }
         _ => {return None;}
@@ -27,9 +66,13 @@ pub fn subtract(left: DataType, right: DataType) -> Option<DataType> {
 
 
 pub fn multiply(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float(x * y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
-            let z:i32 = left.store.integer.unwrap() * right.store.integer.unwrap();
+            let z:i32 = left.store.integer.unwrap().checked_mul(right.store.integer.unwrap())?;
             return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
         }
         _ => {return None;}
@@ -38,9 +81,13 @@ pub fn multiply(left: DataType, right: DataType) -> Option<DataType> {
 
 
 pub fn divide(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float(x / y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
-            let z:i32 = left.store.integer.unwrap() / right.store.integer.unwrap();
+            let z:i32 = left.store.integer.unwrap().checked_div(right.store.integer.unwrap())?;
             return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
         }
         _ => {return None;}
@@ -48,7 +95,134 @@ pub fn divide(left: DataType, right: DataType) -> Option<DataType> {
 }
 
 
+// `a ~/ b` rounds down, unlike `/` which rounds ints towards zero
+pub fn floor_divide(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float((x / y).floor()));}
+        None => {}
+    }
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            let x = left.store.integer.unwrap();
+            let y = right.store.integer.unwrap();
+            let mut z: i32 = x.checked_div(y)?;
+            if x % y != 0 && (x < 0) != (y < 0) {
+                z -= 1;
+            }
+            return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn modulo(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float(x % y));}
+        None => {}
+    }
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            let z:i32 = left.store.integer.unwrap().checked_rem(right.store.integer.unwrap())?;
+            return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn power(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::float(x.powf(y)));}
+        None => {}
+    }
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            let exponent: u32 = right.store.integer.unwrap().try_into().ok()?;
+            let z:i32 = left.store.integer.unwrap().checked_pow(exponent)?;
+            return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn bit_and(left: DataType, right: DataType) -> Option<DataType> {
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            return Some(DataType::int(left.store.integer.unwrap() & right.store.integer.unwrap()));
+        }
+        (Literal::Bool, Literal::Bool) => {
+            return Some(DataType::bool(left.store.bool.unwrap() & right.store.bool.unwrap()));
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn bit_or(left: DataType, right: DataType) -> Option<DataType> {
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            return Some(DataType::int(left.store.integer.unwrap() | right.store.integer.unwrap()));
+        }
+        (Literal::Bool, Literal::Bool) => {
+            return Some(DataType::bool(left.store.bool.unwrap() | right.store.bool.unwrap()));
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn bit_xor(left: DataType, right: DataType) -> Option<DataType> {
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            return Some(DataType::int(left.store.integer.unwrap() ^ right.store.integer.unwrap()));
+        }
+        (Literal::Bool, Literal::Bool) => {
+            return Some(DataType::bool(left.store.bool.unwrap() ^ right.store.bool.unwrap()));
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn bit_not(right: DataType) -> Option<DataType> {
+    match right.kind {
+        Literal::Int => {
+            return Some(DataType::int(!right.store.integer.unwrap()));
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn shift_left(left: DataType, right: DataType) -> Option<DataType> {
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            let shift: u32 = right.store.integer.unwrap().try_into().ok()?;
+            return Some(DataType::int(left.store.integer.unwrap().checked_shl(shift)?));
+        }
+        _ => {return None;}
+    }
+}
+
+
+pub fn shift_right(left: DataType, right: DataType) -> Option<DataType> {
+    match (left.kind, right.kind) {
+        (Literal::Int, Literal::Int) => {
+            let shift: u32 = right.store.integer.unwrap().try_into().ok()?;
+            return Some(DataType::int(left.store.integer.unwrap().checked_shr(shift)?));
+        }
+        _ => {return None;}
+    }
+}
+
+
 pub fn equals(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::bool(x == y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
             let z:bool = left.store.integer.unwrap() == right.store.integer.unwrap();
@@ -62,12 +236,20 @@ pub fn equals(left: DataType, right: DataType) -> Option<DataType> {
             let z:bool = left.value == right.value;
             return Some(DataType{value: z.to_string(), kind: Literal::Bool, store: DataStore::new(None, Some(z))});
         }
-        _ => {return None;}
+        (Literal::Nil, Literal::Nil) => {
+            return Some(DataType::bool(true));
+        }
+        // Values of different types are never equal
+        _ => {return Some(DataType::bool(false));}
     }
 }
 
 
 pub fn lesser(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::bool(x < y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
             let z:bool = left.store.integer.unwrap() < right.store.integer.unwrap();
@@ -79,6 +261,10 @@ pub fn lesser(left: DataType, right: DataType) -> Option<DataType> {
 
 
 pub fn greater(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::bool(x > y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
             let z:bool = left.store.integer.unwrap() > right.store.integer.unwrap();
@@ -90,6 +276,10 @@ pub fn greater(left: DataType, right: DataType) -> Option<DataType> {
 
 
 pub fn equal_lesser(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::bool(x <= y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
             let z:bool = left.store.integer.unwrap() <= right.store.integer.unwrap();
@@ -101,6 +291,10 @@ pub fn equal_lesser(left: DataType, right: DataType) -> Option<DataType> {
 
 
 pub fn equal_greater(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::bool(x >= y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
             let z:bool = left.store.integer.unwrap() >= right.store.integer.unwrap();
@@ -121,7 +315,24 @@ pub fn not(right: DataType) -> Option<DataType> {
     }
 }
 
+pub fn negate(right: DataType) -> Option<DataType> {
+    match right.kind {
+        Literal::Float => {
+            return Some(DataType::float(-right.store.float.unwrap()));
+        }
+        Literal::Int => {
+            let z:i32 = right.store.integer.unwrap().checked_neg()?;
+            return Some(DataType{value: z.to_string(), kind: Literal::Int, store: DataStore::new(Some(z), None)});
+        }
+        _ => {return None;}
+    }
+}
+
 pub fn not_equal(left: DataType, right: DataType) -> Option<DataType> {
+    match floats(&left, &right) {
+        Some((x, y)) => {return Some(DataType::bool(x != y));}
+        None => {}
+    }
     match (left.kind, right.kind) {
         (Literal::Int, Literal::Int) => {
             let z:bool = left.store.integer.unwrap() != right.store.integer.unwrap();
@@ -135,39 +346,192 @@ pub fn not_equal(left: DataType, right: DataType) -> Option<DataType> {
             let z:bool = left.value != right.value;
             return Some(DataType{value: z.to_string(), kind: Literal::Bool, store: DataStore::new(None, Some(z))});
         }
-        _ => {return None;}
+        (Literal::Nil, Literal::Nil) => {
+            return Some(DataType::bool(false));
+        }
+        _ => {return Some(DataType::bool(true));}
+    }
+}
+
+// false, 0, 0.0, "", [] and nil are false, every other value is true
+pub fn truthy(value: &DataType) -> bool {
+    match value.kind {
+        Literal::Bool => {return value.store.bool.unwrap();}
+        Literal::Int => {return value.store.integer.unwrap() != 0;}
+        Literal::Float => {return value.store.float.unwrap() != 0.0;}
+        Literal::String => {return !value.value.is_empty();}
+        Literal::Array => {return !value.store.array.as_ref().map_or(true, |array| array.is_empty());}
+        Literal::Function => {return true;}
+        _ => {return false;}
     }
 }
 
-pub fn and(left: DataType, right: DataType) -> Option<DataType> {
+pub fn range(left: DataType, right: DataType) -> Option<DataType> {
     match (left.kind, right.kind) {
-        (Literal::Bool, Literal::Bool) => {
-            let z:bool = left.store.bool.unwrap() && right.store.bool.unwrap();
-            return Some(DataType{value: z.to_string(), kind: Literal::Bool, store: DataStore::new(None, Some(z))});
+        (Literal::Int, Literal::Int) => {
+            let mut store: Vec<Box<Expr>> = Vec::new();
+            for z in left.store.integer.unwrap()..right.store.integer.unwrap() {
+                store.push(Box::new(Expr::Literal(DataType::int(z))));
+            }
+            return Some(DataType::array(store));
         }
         _ => {return None;}
     }
 }
 
-
-pub fn or(left: DataType, right: DataType) -> Option<DataType> {
-    match (left.kind, right.kind) {
-        (Literal::Bool, Literal::Bool) => {
-            let z:bool = left.store.bool.unwrap() || right.store.bool.unwrap();
-            return Some(DataType{value: z.to_string(), kind: Literal::Bool, store: DataStore::new(None, Some(z))});
+pub fn index(left: DataType, right: DataType) -> Result<Option<DataType>, String> {
+    match left.kind {
+        Literal::String => {
+            let chars: Vec<char> = left.value.chars().collect();
+            let i = resolve_index(right, chars.len())?;
+            return Ok(Some(DataType::string(chars[i].to_string())));
+        }
+        Literal::Array => {
+            let array = left.store.array.unwrap();
+            let i = resolve_index(right, array.len())?;
+            return Ok(array[i].expand());
+        }
+        _ => {
+            return Err(format!("INDEX ERROR: Values of type {:?} can't be indexed", left.kind));
         }
-        _ => {return None;}
     }
 }
 
-pub fn index(left: DataType, right: DataType) -> Option<DataType> {
-    let array = &left.store.array.unwrap();
-    let mut index_int = right.store.integer.unwrap();
+// Turns a possibly negative index into a position, erroring when it falls outside of the value
+pub fn resolve_index(index: DataType, length: usize) -> Result<usize, String> {
+    let Some(mut index_int) = index.store.integer else {
+        return Err(format!("INDEX ERROR: Indexes have to be integers, got {}", index.value));
+    };
+    let length_int: i32 = length.try_into().unwrap();
     if index_int < 0 {
-        let length: i32 = array.len().try_into().unwrap(); 
-        index_int = length + index_int; 
+        index_int = length_int + index_int;
+    }
+    if index_int < 0 || index_int >= length_int {
+        return Err(format!("INDEX ERROR: Index {} is out of range for length {}", index.value, length));
+    }
+    return Ok(index_int.try_into().unwrap());
+}
+
+// Python style slicing, bounds that are out of range get clamped instead of erroring
+pub fn slice(data: DataType, start: Option<DataType>, end: Option<DataType>, step: Option<DataType>) -> Result<Option<DataType>, String> {
+    let length: i32;
+    match data.kind {
+        Literal::String => {length = data.value.chars().count().try_into().unwrap();}
+        Literal::Array => {length = data.store.array.as_ref().unwrap().len().try_into().unwrap();}
+        _ => {return Err(format!("SLICE ERROR: Values of type {:?} can't be sliced", data.kind));}
+    }
+    let step_int = slice_bound(step, 1, length, false)?;
+    if step_int == 0 {
+        return Err("SLICE ERROR: The step of a slice can't be zero".to_string());
+    }
+    let mut positions: Vec<usize> = Vec::new();
+    if step_int > 0 {
+        let start_int = slice_bound(start, 0, length, true)?.clamp(0, length);
+        let end_int = slice_bound(end, length, length, true)?.clamp(0, length);
+        let mut i = start_int;
+        while i < end_int {
+            positions.push(i.try_into().unwrap());
+            // A step past the end of the int range ends the slice too
+            match i.checked_add(step_int) {
+                Some(next) => {i = next;}
+                None => {break;}
+            }
+        }
+    } else {
+        let start_int = slice_bound(start, length - 1, length, true)?.clamp(-1, length - 1);
+        let end_int = slice_bound(end, -1, length, true)?.clamp(-1, length - 1);
+        let mut i = start_int;
+        while i > end_int {
+            positions.push(i.try_into().unwrap());
+            // A step past the end of the int range ends the slice too
+            match i.checked_add(step_int) {
+                Some(next) => {i = next;}
+                None => {break;}
+            }
+        }
+    }
+    match data.kind {
+        Literal::String => {
+            let chars: Vec<char> = data.value.chars().collect();
+            return Ok(Some(DataType::string(positions.iter().map(|i| chars[*i]).collect())));
+        }
+        _ => {
+            let array = data.store.array.unwrap();
+            return Ok(Some(DataType::array(positions.iter().map(|i| array[*i].clone()).collect())));
+        }
+    }
+}
+
+fn slice_bound(bound: Option<DataType>, default: i32, length: i32, negative_from_end: bool) -> Result<i32, String> {
+    let Some(data) = bound else { return Ok(default); };
+    match data.kind {
+        Literal::Nil => {return Ok(default);}
+        _ => {}
+    }
+    let Some(bound_int) = data.store.integer else {
+        return Err(format!("SLICE ERROR: Slice bounds have to be integers, got {}", data.value));
+    };
+    if negative_from_end && bound_int < 0 {
+        return Ok(length + bound_int);
+    }
+    return Ok(bound_int);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i32) -> Option<DataType> {
+        return Some(DataType::int(value));
+    }
+
+    fn sliced(text: &str, start: Option<DataType>, end: Option<DataType>, step: Option<DataType>) -> String {
+        return slice(DataType::string(text.to_string()), start, end, step).unwrap().unwrap().value;
+    }
+
+    #[test]
+    fn bounds_pick_a_range() {
+        assert_eq!(sliced("hello", int(1), int(3), None), "el");
+        assert_eq!(sliced("hello", None, None, None), "hello");
+    }
+
+    #[test]
+    fn negative_bounds_count_from_the_end() {
+        assert_eq!(sliced("hello", int(-3), None, None), "llo");
+        assert_eq!(sliced("hello", None, int(-1), None), "hell");
+    }
+
+    #[test]
+    fn bounds_out_of_range_are_clamped() {
+        assert_eq!(sliced("hello", int(-100), int(100), None), "hello");
+        assert_eq!(sliced("hello", int(4), int(2), None), "");
+        assert_eq!(sliced("hello", int(i32::MIN), int(i32::MAX), None), "hello");
+    }
+
+    #[test]
+    fn negative_steps_walk_backwards() {
+        assert_eq!(sliced("hello", None, None, int(-1)), "olleh");
+        assert_eq!(sliced("hello", int(100), int(-100), int(-2)), "olh");
+    }
+
+    #[test]
+    fn steps_past_the_int_range_stop_the_slice() {
+        assert_eq!(sliced("hello", None, None, int(i32::MAX)), "h");
+        assert_eq!(sliced("hello", None, None, int(i32::MIN)), "o");
+    }
+
+    #[test]
+    fn zero_steps_and_non_integer_bounds_are_errors() {
+        assert!(slice(DataType::string("hello".to_string()), None, None, int(0)).is_err());
+        assert!(slice(DataType::string("hello".to_string()), Some(DataType::string("a".to_string())), None, None).is_err());
+        assert!(slice(DataType::int(5), None, None, None).is_err());
+    }
+
+    #[test]
+    fn arrays_slice_like_strings() {
+        let array = DataType::array((1..=5).map(|value| Box::new(Expr::Literal(DataType::int(value)))).collect());
+        let every_other = slice(array, None, None, int(2)).unwrap().unwrap();
+        let values: Vec<String> = every_other.store.array.unwrap().iter().map(|value| value.expand().unwrap().value).collect();
+        assert_eq!(values, vec!["1", "3", "5"]);
     }
-    let i: usize = index_int.try_into().unwrap();
-    let value = &array[i];
-    return value.expand();
 }
@@ -1,170 +1,310 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::mem;
+use std::rc::Rc;
 use std::process::Command;
 
+use once_cell::sync::Lazy;
+
 use crate::parsing::parser::*;
+use crate::parsing::resolver::members;
 use crate::std_lib::std_lib::*;
 use crate::runtime::operations::*;
 use crate::runtime::functions::*;
 
-pub fn run(tree: &Vec<Box<Expr>>, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    let mut if_status = false;
-    let mut if_started = false;
+// How a block finished running, anything but Normal unwinds until something handles it
+#[derive(Debug, Clone)]
+pub enum Flow {
+    Normal,
+    Return(DataType),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Exit(i32),
+    Error(String),
+    // `return f(...)` inside of a function, the call and its arguments are handed back instead of nesting deeper
+    TailCall(Rc<Closure>, HashMap<String, DataType>),
+}
+
+// The most nested calls RASH_MAX_DEPTH can ask for, every one of them needs room on the native stack
+pub const DEPTH_LIMIT: usize = 10_000;
+
+// How many function calls can be nested, tail calls don't count
+pub static MAX_DEPTH: Lazy<usize> = Lazy::new(|| {
+    return max_depth().unwrap_or(1000);
+});
+
+// Reads RASH_MAX_DEPTH, it has to be a whole number from 1 to DEPTH_LIMIT
+pub fn max_depth() -> Result<usize, String> {
+    let Ok(setting) = env::var("RASH_MAX_DEPTH") else { return Ok(1000); };
+    match setting.trim().parse::<usize>() {
+        Ok(depth) if (1..=DEPTH_LIMIT).contains(&depth) => {return Ok(depth);}
+        _ => {return Err(format!("RECURSION ERROR: RASH_MAX_DEPTH has to be a whole number from 1 to {}, not {}", DEPTH_LIMIT, setting));}
+    }
+}
+
+impl From<String> for Flow {
+    fn from(error: String) -> Self {
+        return Flow::Error(error);
+    }
+}
+
+pub fn run(tree: &[Box<Expr>], scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Flow {
+    match run_tree(tree, scopes, functions) {
+        Ok(flow) | Err(flow) => {return flow;}
+    }
+}
+
+fn run_tree(tree: &[Box<Expr>], scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Flow, Flow> {
     for branch in tree {
-        match *branch.clone() {
+        match &**branch {
             Expr::Binary(expr) => {
                 match expr.operator {
                     Operator::Equals => {
                         let name: DataType = expr.left.expand().expect("Where did the name go");
-                        let output = calculate_bexpr(&expr.right, scopes, functions).unwrap();
+                        let output = calculate_bexpr(&expr.right, scopes, functions)?.unwrap_or(DataType::new());
+                        set_into_scope(scopes, scopes.len()-1, name.value.as_str(), output.clone());
+                        match &*expr.right {
+                            Expr::Definition(definition) => {bind_self(definition, &name.value, &output, scopes);}
+                            _ => {}
+                        }
+                    }
+                    // `exists || c"touch file"` is run for what it does
+                    _ => {
+                        calculate_bexpr(branch, scopes, functions)?;
+                    }
+                }
+            }
+            Expr::Unary(expr) => {
+                match expr.operator {
+                    Operator::Plus | Operator::Minus => {
+                        let output = calculate_unexpr(branch, scopes)?.unwrap_or(DataType::new());
+                        let name: DataType = expr.value.expand().unwrap_or(DataType::new());
                         set_into_scope(scopes, scopes.len()-1, name.value.as_str(), output);
                     }
-                    _ => {}
+                    _ => {
+                        calculate_bexpr(branch, scopes, functions)?;
+                    }
                 }
             }
             Expr::Block(expr) => {
+                let mut output = Flow::Normal;
                 match expr.kind {
-                    BlockType::If => {
-                        let output: Option<DataType>;
-                        (if_status, output) = run_if(&expr, scopes, functions).expect("Error");
-                        match output {
-                            Some(..) => {return output;}
-                            _ => {}
-                        }
-                        if_started = true;
+                    BlockType::IfChain => {
+                        run_if_chain(&expr, scopes, functions)?;
                     }
-                    BlockType::ElseIf => {
-                        if if_started && !if_status {
-                            let output: Option<DataType>;
-                            (if_status, output) = run_if(&expr, scopes, functions).expect("Error");
-                            match output {
-                                Some(..) => {return output;}
-                                _ => {}
-                            }
-                        }
+                    BlockType::Match => {
+                        run_match(&expr, scopes, functions)?;
                     }
-
-                    BlockType::Else => {
-                        if if_started && !if_status {
-                            let output = run_else(&expr, scopes, functions).expect("Error");
-                            match output {
-                                Some(..) => {return output;}
-                                _ => {}
-                            }
-                        }
-                        if_status = false;
-                        if_started = false;
+                    BlockType::Conditional => {
+                        run_conditional(&expr, scopes, functions)?;
+                    }
+                    BlockType::Try => {
+                        run_try(&expr, scopes, functions)?;
+                    }
+                    BlockType::Catch => {
+                        return Err(Flow::Error("SYNTAX ERROR: catch has to follow a try block".to_string()));
+                    }
+                    BlockType::If | BlockType::ElseIf | BlockType::Else => {
+                        return Err(Flow::Error("SYNTAX ERROR: else has to follow an if or else if block".to_string()));
                     }
 
                     BlockType::For => {
-                        if_status = false;
-                        if_started = false;
-                        let output = run_for(&expr, scopes, functions).expect("Error");
-                        match output {
-                            Some(..) => {return output;}
+                        output = run_for(&expr, scopes, functions);
+                    }
+                    BlockType::While | BlockType::Loop => {
+                        output = run_while(&expr, scopes, functions);
+                    }
+                    BlockType::Let => {
+                        let (name, value) = declared(&expr, scopes, functions)?;
+                        let index = scopes.len()-1;
+                        let value = value.unwrap_or(DataType::new());
+                        set_into_current_scope(scopes, index, name.clone(), value.clone());
+                        match expr.block.first().map(|declaration| &**declaration) {
+                            Some(Expr::Binary(BinaryExpr{right, ..})) => {
+                                match &**right {
+                                    Expr::Definition(definition) => {bind_self(definition, &name, &value, scopes);}
+                                    _ => {}
+                                }
+                            }
                             _ => {}
                         }
                     }
+                    BlockType::Global => {
+                        let (name, value) = declared(&expr, scopes, functions)?;
+                        let index = scopes.len()-1;
+                        // The global scope itself doesn't need an alias
+                        if index > 0 {
+                            set_into_current_scope(scopes, index, name.clone(), DataType{value: name.clone(), kind: Literal::Variable, store: DataStore::new(None, None)});
+                        }
+                        match value {
+                            Some(value) => {set_into_scope(scopes, index, name.as_str(), value);}
+                            None => {}
+                        }
+                    }
+                    BlockType::Break => {
+                        output = Flow::Break(expr.label.clone());
+                    }
+                    BlockType::Continue => {
+                        output = Flow::Continue(expr.label.clone());
+                    }
+                    BlockType::ForIn => {
+                        output = run_for_in(&expr, scopes, functions);
+                    }
                     BlockType::FormatedString => {
-                        if_status = false;
-                        if_started = false;
-                        format_string(&expr, scopes);
+                        format_string(&expr, scopes)?;
                     }
 
                     BlockType::CommandString => {
-                        if_status = false;
-                        if_started = false;
-                        shell_string(&expr, scopes, true);
+                        shell_string(&expr, scopes, true)?;
                     }
                     BlockType::Import => {
-                        import(&expr, functions).expect("ERROR");
+                        import(&expr, functions)?;
                     }
                     BlockType::Return => {
-                        return run_return(&expr, scopes, functions);
+                        output = run_return(&expr, scopes, functions)?;
                     }
                     _ => {} 
                 }
+                match output {
+                    Flow::Normal => {}
+                    _ => {return Ok(output);}
+                }
             }
-            Expr::Function(mut expr) => {
-                if_status = false;
-                if_started = false;
+            Expr::Function(expr) => {
                 match expr.kind {
                     FunctionType::Print => {
-                        run_print(&expr, scopes, functions)
-                    }
-                    FunctionType::Length => {
-                        run_len(&expr, scopes, functions);
-                    }
-                    FunctionType::Pop => {
-                        run_pop(&expr, scopes, functions);
-                    }
-                    FunctionType::Push => {
-                        run_push(&expr, scopes, functions);
+                        run_print(expr, scopes, functions)?;
                     }
-                    FunctionType::Swap => {
-                        run_swap(&expr, scopes, functions);
-                    }
-                    FunctionType::Int => {
-                        run_int(&expr, scopes, functions);
-                    }
-                    FunctionType::String => {
-                        run_string(&expr, scopes, functions);
-                    }
-                    FunctionType::Delete => {
-                        run_delete(&expr, scopes, functions);
-                    }
-                    FunctionType::Defined => {
-                        run_function(&mut expr, scopes, functions).expect("Error");
+                    _ => {
+                        calculate_bexpr(branch, scopes, functions)?;
                     } 
-                    _ => {} 
+                }
+            }
+            // Inside of a function it's made again with the variables there by now, and only that call has it
+            Expr::Definition(expr) if call_depth(scopes) > 0 => {
+                if !expr.name.is_empty() {
+                    let value = make_closure(expr, scopes);
+                    scopes[1].insert(expr.name.clone(), value.clone());
+                    bind_self(expr, &expr.name, &value, scopes);
                 }
             }
             Expr::Definition(expr) => {
-                if_status = false;
-                if_started = false;
-                functions.insert(expr.name.clone(), expr);
+                functions.insert(expr.name.clone(), expr.clone());
             }
             _ => {},
         }
     }
-    return None;
+    return Ok(Flow::Normal);
+}
+
+// The name a let or global declares and the value it's given, if any
+fn declared(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(String, Option<DataType>), Flow> {
+    match expr.block.first().map(|declaration| &**declaration) {
+        Some(Expr::Binary(BinaryExpr{operator: Operator::Equals, left, right})) => {
+            let value = calculate_bexpr(right, scopes, functions)?.unwrap_or(DataType::new());
+            return Ok((left.expand().unwrap().value, Some(value)));
+        }
+        Some(Expr::Literal(name)) => {return Ok((name.value.clone(), None));}
+        _ => {return Err(Flow::Error("SYNTAX ERROR: let and global need a variable name".to_string()));}
+    }
 }
 
-pub fn calculate_bexpr(in_expr: &Expr, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
+pub fn calculate_bexpr(in_expr: &Expr, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
     let expr: &BinaryExpr;
     match in_expr {
         Expr::Binary(x) => {expr = x;}
+        Expr::Unary(UnaryExpr{operator: Operator::Not, value}) => {
+            return Ok(Some(DataType::bool(!run_condition(value, scopes, functions)?)));
+        }
+        Expr::Unary(x) => {
+            let value = calculate_bexpr(&x.value, scopes, functions)?.unwrap_or(DataType::new());
+            let output: Option<DataType>;
+            match x.operator {
+                Operator::Negate => {
+                    output = negate(value.clone());
+                }
+                Operator::BitNot => {
+                    output = bit_not(value.clone());
+                }
+                _ => {
+                    return calculate_unexpr(in_expr, scopes);
+                }
+            }
+            match output {
+                Some(_) => {return Ok(output);}
+                None => {return Err(operator_error(&x.operator, &[value]));}
+            }
+        }
         Expr::Literal(lit) => { 
             match lit.kind {
                 Literal::Variable => {
-                    return get_from_scope(scopes, lit.value.as_str()).expect("ERROR");
+                    match get_from_scope(scopes, lit.value.as_str()) {
+                        Ok(value) => {return Ok(value);}
+                        Err(error) => {
+                            // A named function can be used as a value too
+                            match functions.get(&lit.value) {
+                                Some(definition) => {
+                                    return Ok(Some(DataType::function(Closure{definition: Rc::clone(definition), captured: RefCell::new(HashMap::new())})));
+                                }
+                                None => {return Err(Flow::Error(error));}
+                            }
+                        }
+                    }
+                }
+                Literal::Array => {
+                    // Elements are evaluated once, so the array holds values rather than expressions
+                    let mut store: Vec<Box<Expr>> = Vec::new();
+                    for value in lit.store.array.as_ref().unwrap().iter() {
+                        let output = calculate_bexpr(&value, scopes, functions)?.unwrap_or(DataType::new());
+                        store.push(Box::new(Expr::Literal(output)));
+                    }
+                    return Ok(Some(DataType::array(store)));
                 }
-                Literal::Int | Literal::Bool | Literal::Array => {
+                Literal::Int | Literal::Bool => {
                     let value = in_expr.expand().unwrap();
-                    return Some(value);
+                    return Ok(Some(value));
                 }
                 _ => {
-                    return in_expr.expand();
+                    return Ok(in_expr.expand());
                 }
             }
         }
         Expr::Block(x) => {
             match x.kind {
                 BlockType::FormatedString => {
-                    return format_string(&x, scopes);
+                    return Ok(format_string(&x, scopes)?);
                 }
                 BlockType::CommandString => {
-                    return shell_string(&x, scopes, false);
+                    return Ok(shell_string(&x, scopes, false)?);
+                }
+                BlockType::IfChain => {
+                    return run_if_chain(&x, scopes, functions);
+                }
+                BlockType::Match => {
+                    return run_match(&x, scopes, functions);
                 }
-                _ => {return None;} 
+                BlockType::Conditional => {
+                    return run_conditional(&x, scopes, functions);
+                }
+                BlockType::Try => {
+                    return run_try(&x, scopes, functions);
+                }
+                _ => {return Ok(None);} 
             }
         }
 
         Expr::Function(x) => {
             match x.kind {
                 FunctionType::Defined => {
-                    let output = run_function(&mut x.clone(), scopes, functions).expect("Error");
+                    let output = run_function(x, scopes, functions);
                     return output;
                 } 
+                // `ok && print(...)` still prints, print itself gives back nil
+                FunctionType::Print => {
+                    run_print(x, scopes, functions)?;
+                    return Ok(None);
+                }
 
                 FunctionType::Length => {
                     let output = run_len(x, scopes, functions);
@@ -197,91 +337,256 @@ pub fn calculate_bexpr(in_expr: &Expr, scopes: &mut Vec<HashMap<String, DataType
                     let output = run_delete(x, scopes, functions);
                     return output;
                 }
-                _ => {return None;} 
+                FunctionType::Slice => {
+                    return run_slice(x, scopes, functions);
+                }
+                FunctionType::Split => {
+                    return run_split(x, scopes, functions);
+                }
+                FunctionType::Join => {
+                    return run_join(x, scopes, functions);
+                }
+                FunctionType::Trim => {
+                    return run_trim(x, scopes, functions);
+                }
+                FunctionType::Replace => {
+                    return run_replace(x, scopes, functions);
+                }
+                FunctionType::Contains => {
+                    return run_contains(x, scopes, functions);
+                }
+                FunctionType::StartsWith => {
+                    return run_starts_with(x, scopes, functions);
+                }
+                FunctionType::EndsWith => {
+                    return run_ends_with(x, scopes, functions);
+                }
+                FunctionType::Upper => {
+                    return run_upper(x, scopes, functions);
+                }
+                FunctionType::Lower => {
+                    return run_lower(x, scopes, functions);
+                }
+                FunctionType::Lines => {
+                    return run_lines(x, scopes, functions);
+                }
+                FunctionType::Exit => {
+                    return Err(run_exit(x, scopes, functions));
+                }
+                FunctionType::Bool => {
+                    return run_bool(x, scopes, functions);
+                }
+                FunctionType::IsNil => {
+                    return run_is_nil(x, scopes, functions);
+                }
+                FunctionType::Type => {
+                    return run_type(x, scopes, functions);
+                }
+                FunctionType::Float => {
+                    return run_float(x, scopes, functions);
+                }
+                FunctionType::Array => {
+                    return run_array(x, scopes, functions);
+                }
+                FunctionType::Map => {
+                    return run_map(x, scopes, functions);
+                }
+                FunctionType::Filter => {
+                    return run_filter(x, scopes, functions);
+                }
+                FunctionType::Reduce => {
+                    return run_reduce(x, scopes, functions);
+                }
+                FunctionType::Sort => {
+                    return run_sort(x, scopes, functions);
+                }
+                FunctionType::SortBy => {
+                    return run_sort_by(x, scopes, functions);
+                }
+                FunctionType::Find => {
+                    return run_find(x, scopes, functions);
+                }
+                FunctionType::Any => {
+                    return run_any(x, scopes, functions);
+                }
+                FunctionType::All => {
+                    return run_all(x, scopes, functions);
+                }
+                FunctionType::Zip => {
+                    return run_zip(x, scopes, functions);
+                }
+                FunctionType::Enumerate => {
+                    return run_enumerate(x, scopes, functions);
+                }
+                FunctionType::Reverse => {
+                    return run_reverse(x, scopes, functions);
+                }
+                FunctionType::Unique => {
+                    return run_unique(x, scopes, functions);
+                }
+                FunctionType::Call => {
+                    return run_function(x, scopes, functions);
+                }
+                _ => {return Ok(None);} 
             }
         }
-        _ => {return None;}
+        Expr::Definition(x) => {
+            return Ok(Some(make_closure(x, scopes)));
+        }
+        _ => {return Ok(None);}
     }
-    let left = calculate_bexpr(&expr.left, scopes, functions);
-    let right = calculate_bexpr(&expr.right, scopes, functions);
+    // The right side of && and || only runs when the left one doesn't already decide the result
+    match expr.operator {
+        Operator::And | Operator::Or => {
+            let left = run_condition(&expr.left, scopes, functions)?;
+            if left == matches!(expr.operator, Operator::Or) {
+                return Ok(Some(DataType::bool(left)));
+            }
+            return Ok(Some(DataType::bool(run_condition(&expr.right, scopes, functions)?)));
+        }
+        // `a ?? b` only looks at b when a is nil
+        Operator::Coalesce => {
+            let left = calculate_bexpr(&expr.left, scopes, functions)?.unwrap_or(DataType::new());
+            match left.kind {
+                Literal::Nil => {return calculate_bexpr(&expr.right, scopes, functions);}
+                _ => {return Ok(Some(left));}
+            }
+        }
+        _ => {}
+    }
+    let left = calculate_bexpr(&expr.left, scopes, functions)?.unwrap_or(DataType::new());
+    let right = calculate_bexpr(&expr.right, scopes, functions)?.unwrap_or(DataType::new());
 
+    let output: Option<DataType>;
     match expr.operator {
         Operator::Plus => {
-            return add(left.unwrap(), right.unwrap());
+            output = add(left.clone(), right.clone());
         }
         Operator::Times => {
-            return multiply(left.unwrap(), right.unwrap());
+            output = multiply(left.clone(), right.clone());
         }
         Operator::Minus => {
-            return subtract(left.unwrap(), right.unwrap());
+            output = subtract(left.clone(), right.clone());
+        }
+        Operator::Divide | Operator::Modulo | Operator::FloorDivide => {
+            // A float zero is as much of a mistake as an int one, neither gives back inf or NaN
+            match as_float(&right) {
+                Some(divisor) if divisor == 0.0 => {return Err(Flow::Error("MATH ERROR: Division by zero".to_string()));}
+                _ => {}
+            }
+            match expr.operator {
+                Operator::Divide => {output = divide(left.clone(), right.clone());}
+                Operator::FloorDivide => {output = floor_divide(left.clone(), right.clone());}
+                _ => {output = modulo(left.clone(), right.clone());}
+            }
+        }
+        Operator::Power => {
+            output = power(left.clone(), right.clone());
+        }
+        Operator::BitAnd => {
+            output = bit_and(left.clone(), right.clone());
+        }
+        Operator::BitOr => {
+            output = bit_or(left.clone(), right.clone());
+        }
+        Operator::BitXor => {
+            output = bit_xor(left.clone(), right.clone());
+        }
+        Operator::ShiftLeft => {
+            output = shift_left(left.clone(), right.clone());
         }
-        Operator::Divide => {
-            return divide(left.unwrap(), right.unwrap());
+        Operator::ShiftRight => {
+            output = shift_right(left.clone(), right.clone());
         }
         Operator::EqualTo => {
-            return equals(left.unwrap(), right.unwrap());
+            output = equals(left.clone(), right.clone());
         }
         Operator::LesserThan => {
-            return lesser(left.unwrap(), right.unwrap());
+            output = lesser(left.clone(), right.clone());
         }
         Operator::GreaterThan => {
-            return greater(left.unwrap(), right.unwrap());
+            output = greater(left.clone(), right.clone());
         }
         Operator::EqualLesser => {
-            return equal_lesser(left.unwrap(), right.unwrap());
+            output = equal_lesser(left.clone(), right.clone());
         }
         Operator::EqualGreater => {
-            return equal_greater(left.unwrap(), right.unwrap());
+            output = equal_greater(left.clone(), right.clone());
         }
         Operator::Not => {
-            return not(right.unwrap());
+            output = not(right.clone());
         }
         Operator::NotEqual => {
-            return not_equal(left.unwrap(), right.unwrap());
+            output = not_equal(left.clone(), right.clone());
         }
-        Operator::And => {
-            return and(left.unwrap(), right.unwrap());
+        Operator::Index => {
+            return Ok(index(left, right)?);
         }
-        Operator::Or => {
-            return or(left.unwrap(), right.unwrap());
+        // `a?[i]` is nil instead of an error when a is nil or i is out of range
+        Operator::OptionalIndex => {
+            match left.kind {
+                Literal::Nil => {return Ok(Some(DataType::new()));}
+                _ => {return Ok(index(left, right).unwrap_or(Some(DataType::new())));}
+            }
         }
-        Operator::Index => {
-            return index(left.unwrap(), right.unwrap());
+        Operator::Range => {
+            output = range(left.clone(), right.clone());
         }
-        _ => {}
+        _ => {return Ok(None);}
+    }
+    match output {
+        Some(_) => {return Ok(output);}
+        None => {return Err(operator_error(&expr.operator, &[left, right]));}
     }
-    return None;
+}
+
+// Integer results that don't fit are a math error, anything else is an operator used on the wrong types
+fn operator_error(operator: &Operator, operands: &[DataType]) -> Flow {
+    let arithmetic = matches!(operator, Operator::Plus | Operator::Minus | Operator::Times | Operator::Divide | Operator::FloorDivide | Operator::Modulo | Operator::Power | Operator::ShiftLeft | Operator::ShiftRight | Operator::Negate);
+    if arithmetic && operands.iter().all(|operand| matches!(operand.kind, Literal::Int)) {
+        return Flow::Error(format!("MATH ERROR: {:?} on {} is out of range", operator, operands.iter().map(|operand| operand.value.clone()).collect::<Vec<String>>().join(" and ")));
+    }
+    let kinds: Vec<String> = operands.iter().map(|operand| format!("{:?}", operand.kind)).collect();
+    return Flow::Error(format!("TYPE ERROR: {:?} can't be used on {}", operator, kinds.join(" and ")));
 }
 
 
-pub fn calculate_unexpr(in_expr: &Expr, scopes: &mut Vec<HashMap<String, DataType>>) -> Option<DataType> {
+// `++name` and `--name` give back the variable plus or minus one, the caller stores it
+pub fn calculate_unexpr(in_expr: &Expr, scopes: &mut Vec<HashMap<String, DataType>>) -> Result<Option<DataType>, Flow> {
     let expr: &UnaryExpr;
     match in_expr {
         Expr::Unary(x) => { expr = x;}
-        _ => {return None;}
+        _ => {return Ok(None);}
     }
-    let value: DataType = get_from_scope(scopes, expr.value.expand().unwrap().value.as_str()).expect("ERROR").unwrap();
+    let Some(name) = expr.value.expand() else {
+        return Err(Flow::Error("SYNTAX ERROR: ++ and -- only work on a variable".to_string()));
+    };
+    let value: DataType = get_from_scope(scopes, name.value.as_str())?.unwrap_or(DataType::new());
     let one: DataType = DataType{value: "1".to_string(), kind: Literal::Int, store: DataStore::new(Some(1), None)};
+    let output: Option<DataType>;
     match expr.operator {
         Operator::Plus => {
-            return add(value, one);
+            output = add(value.clone(), one.clone());
         }
         Operator::Minus => {
-            return subtract(value, one);
+            output = subtract(value.clone(), one.clone());
         }
-        _ => {}
+        _ => {return Ok(None);}
+    }
+    match output {
+        Some(_) => {return Ok(output);}
+        None => {return Err(operator_error(&expr.operator, &[value, one]));}
     }
-    return None;
 }
 
-pub fn format_string(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>) -> Option<DataType> {
+pub fn format_string(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>) -> Result<Option<DataType>, String> {
     let mut value: DataType = DataType { value: "".to_string(), kind: Literal::String, store: DataStore::new(None, None) };
-    for content in expr.block.clone() {
-        match *content {
+    for content in &expr.block {
+        match &**content {
             Expr::Literal(x) => {
                 match x.kind {
                     Literal::Variable => {
-                        value.value += &get_from_scope(scopes, &x.value).expect("ERROR")?.value;
+                        value.value += &get_from_scope(scopes, &x.value)?.unwrap().value;
                     }
                     Literal::String => {value.value += &x.value}
                     _ => {}
@@ -290,17 +595,27 @@ pub fn format_string(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>)
             _ => {} 
         }
     }
-    return Some(value);
+    return Ok(Some(value));
+}
+
+pub fn shell_string(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, print_out: bool) -> Result<Option<DataType>, String> {
+    let output = shell_command(expr, scopes)?;
+    let stdout_str = String::from_utf8_lossy(&output.stdout).to_string().trim().to_string();
+    let stdout = DataType{value: stdout_str, kind: Literal::String, store: DataStore::new(None, None)};
+    if print_out {
+        print!("{}", stdout.value);
+    }
+    return Ok(Some(stdout));
 }
 
-pub fn shell_string(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, print_out: bool) -> Option<DataType> {
+fn shell_command(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>) -> Result<std::process::Output, String> {
     let mut value: DataType = DataType { value: "".to_string(), kind: Literal::String, store: DataStore::new(None, None)};
-    for content in expr.block.clone() {
-        match *content {
+    for content in &expr.block {
+        match &**content {
             Expr::Literal(x) => {
                 match x.kind {
                     Literal::Variable => {
-                        value.value += &get_from_scope(scopes, &x.value).expect("ERROR")?.value;
+                        value.value += &get_from_scope(scopes, &x.value)?.unwrap().value;
                     }
                     Literal::String => {value.value += &x.value}
                     _ => {}
@@ -321,136 +636,746 @@ pub fn shell_string(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, p
             .output()
             .expect("failed to execute process")
     };
-    let stdout_str = String::from_utf8_lossy(&output.stdout).to_string().trim().to_string();
-    let stdout = DataType{value: stdout_str, kind: Literal::String, store: DataStore::new(None, None)};
-    if print_out {
-        print!("{}", stdout.value);
+    return Ok(output);
+}
+
+// A command used as a condition holds when it exits successfully, anything else goes by its truthiness
+pub fn run_condition(expr: &Expr, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<bool, Flow> {
+    match expr {
+        Expr::Block(block @ Block{kind: BlockType::CommandString, ..}) => {
+            return Ok(shell_command(block, scopes)?.status.success());
+        }
+        _ => {
+            let value = calculate_bexpr(expr, scopes, functions)?.unwrap_or(DataType::new());
+            return Ok(truthy(&value));
+        }
     }
-    return Some(stdout);
 }
 
-pub fn run_function<'a>(call: &mut Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Result<Option<DataType>, &'a str> {
-    scopes.push(HashMap::new());
-    let expr = functions.get(&call.name).unwrap(); 
-    if expr.arguments.len() != call.arguments.len() {
-        return Err("INVALID ARGUMENTS: Invalid ammount of arguments to this function");
+pub fn run_function(call: &Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let (closure, arguments) = callee(call, scopes, functions)?;
+    return call_closure(&closure, arguments, scopes, functions);
+}
+
+// The function a call goes to and the arguments meant for it, `callee(arguments)` calls have the callee as their first argument
+fn callee<'a>(call: &'a Function, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(Rc<Closure>, &'a [Box<Expr>]), Flow> {
+    match call.kind {
+        FunctionType::Call => {
+            let value = calculate_bexpr(&call.arguments[0], scopes, functions)?.unwrap_or(DataType::new());
+            match &value.store.function {
+                Some(closure) => {return Ok((Rc::clone(closure), &call.arguments[1..]));}
+                None => {return Err(Flow::Error(format!("TYPE ERROR: {} can't be called", type_name(&value.kind))));}
+            }
+        }
+        _ => {}
     }
-    for i in 0..call.arguments.len() {
-        let output = calculate_bexpr(&call.arguments[i], scopes, &mut functions.clone()).unwrap();
-        let name = expr.arguments[i].expand().unwrap();
-        set_into_scope(scopes, scopes.len()-1, name.value.as_str(), output);
+    // A variable holding a function shadows a named function with the same name
+    match get_from_scope(scopes, &call.name) {
+        Ok(Some(value)) => {
+            match &value.store.function {
+                Some(closure) => {return Ok((Rc::clone(closure), &call.arguments));}
+                None => {return Err(Flow::Error(format!("TYPE ERROR: {} {} can't be called", type_name(&value.kind), call.name)));}
+            }
+        }
+        _ => {}
+    }
+    match functions.get(&call.name) {
+        Some(definition) => {
+            let closure = Closure{definition: Rc::clone(definition), captured: RefCell::new(HashMap::new())};
+            return Ok((Rc::new(closure), &call.arguments));
+        }
+        None => {return Err(Flow::Error(format!("FUNCTION NOT FOUND: {} wasn't found", call.name)));}
     }
-    let output = run(&expr.block.clone(), scopes, functions);
-    scopes.pop();
-    return Ok(output);
 }
 
-pub fn run_return(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Option<DataType> {
-    if expr.block.len() == 0 {
-        return Some(DataType::new());
+pub fn call_closure(closure: &Rc<Closure>, arguments: &[Box<Expr>], scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let values = bind_arguments(&closure.definition, arguments, scopes, functions)?;
+    return call_with(closure, values, scopes, functions);
+}
+
+// Calls a function with its arguments already worked out
+fn call_with(closure: &Rc<Closure>, mut values: HashMap<String, DataType>, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let depth = call_depth(scopes) + 1;
+    if depth > *MAX_DEPTH {
+        return Err(Flow::Error(format!("RECURSION ERROR: {} went deeper than {} nested calls, RASH_MAX_DEPTH can raise the limit", function_name(&closure.definition), *MAX_DEPTH)));
+    }
+    let mut closure = Rc::clone(closure);
+    // The body only sees the globals and its own scope, never the caller's locals
+    let caller = scopes.split_off(1);
+    let mut output = run_frame(&closure, values, depth, scopes, functions);
+    // Tail calls replace the frame they came from, so they run here instead of on top of it
+    while let Err(Flow::TailCall(next, next_values)) = output {
+        closure = next;
+        values = next_values;
+        output = run_frame(&closure, values, depth, scopes, functions);
+    }
+    scopes.extend(caller);
+    match output {
+        Ok(value) => {return Ok(Some(value.unwrap_or(DataType::new())));}
+        Err(Flow::Return(value)) => {return Ok(Some(value));}
+        Err(Flow::Break(..) | Flow::Continue(..)) => {return Err(Flow::Error("LOOP ERROR: break and continue can only be used inside of a loop".to_string()));}
+        Err(flow) => {return Err(flow);}
+    }
+}
+
+// Arguments are evaluated where the call happens, before the function's scope exists
+fn bind_arguments(expr: &Definition, arguments: &[Box<Expr>], scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<HashMap<String, DataType>, Flow> {
+    let parameters = parameters(expr);
+    let mut values: HashMap<String, DataType> = HashMap::new();
+    let mut rest: Vec<Box<Expr>> = Vec::new();
+    let mut position: usize = 0;
+    for argument in arguments {
+        match &**argument {
+            // `f(name = value)` passes an argument by name
+            Expr::Binary(BinaryExpr{operator: Operator::Equals, left, right}) => {
+                let name = left.expand().unwrap().value;
+                if !parameters.iter().any(|(parameter, _)| *parameter == name) {
+                    return Err(Flow::Error(format!("INVALID ARGUMENTS: {} has no parameter called {}", function_name(expr), name)));
+                }
+                if values.contains_key(&name) {
+                    return Err(Flow::Error(format!("INVALID ARGUMENTS: {} was given more than once", name)));
+                }
+                let output = calculate_bexpr(right, scopes, functions)?.unwrap_or(DataType::new());
+                values.insert(name, output);
+            }
+            _ => {
+                let output = calculate_bexpr(argument, scopes, functions)?.unwrap_or(DataType::new());
+                while position < parameters.len() && values.contains_key(&parameters[position].0) {
+                    position += 1;
+                }
+                if position < parameters.len() {
+                    values.insert(parameters[position].0.clone(), output);
+                    position += 1;
+                } else if expr.rest.is_some() {
+                    rest.push(Box::new(Expr::Literal(output)));
+                } else {
+                    return Err(Flow::Error(format!("INVALID ARGUMENTS: {} takes at most {} arguments", function_name(expr), parameters.len())));
+                }
+            }
+        }
+    }
+    match &expr.rest {
+        Some(name) => {values.insert(name.clone(), DataType::array(rest));}
+        None => {}
+    }
+    return Ok(values);
+}
+
+// Runs one call on top of the globals, leaving only the globals behind
+fn run_frame(closure: &Closure, values: HashMap<String, DataType>, depth: usize, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let expr = &closure.definition;
+    let mut locals: HashMap<String, DataType> = HashMap::new();
+    for (name, value) in closure.captured.borrow().iter() {
+        if !parameters_named(expr, name) {
+            locals.insert(name.clone(), value.clone());
+        }
+    }
+    locals.extend(values);
+    locals.insert(FRAME.to_string(), DataType::int(depth as i32));
+    // A function uses the globals of the file it's from, the ones in place wait in their own file's scope
+    // so a function from there called further down can still find them
+    let mut outer: Option<Rc<ModuleScope>> = None;
+    match &expr.module {
+        Some(module) if scopes[0].get(MODULE).map(|path| path.value != module.path).unwrap_or(true) => {
+            let previous = owner(&scopes[0]);
+            *previous.globals.borrow_mut() = mem::replace(&mut scopes[0], module.globals.take());
+            outer = Some(previous);
+        }
+        _ => {}
+    }
+    scopes.push(locals);
+    hoist(&expr.block, scopes);
+    let output = bind_defaults(expr, scopes, functions).and_then(|_| run_branch(&expr.block, scopes, functions));
+    // Whatever the body did to the captured variables is there for the next call
+    for (name, value) in closure.captured.borrow_mut().iter_mut() {
+        match scopes[1].get(name) {
+            Some(local) if !parameters_named(expr, name) => {*value = local.clone();}
+            _ => {}
+        }
+    }
+    scopes.truncate(1);
+    match (outer, &expr.module) {
+        (Some(outer), Some(module)) => {*module.globals.borrow_mut() = mem::replace(&mut scopes[0], outer.globals.take());}
+        _ => {}
     }
-    let output = calculate_bexpr(&expr.block[0], scopes, functions);
     return output;
 }
 
-pub fn run_if(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Result<(bool, Option<DataType>), String> {
+// The functions a body defines can be called anywhere in it, so they're made before it runs.
+// Each of them also gets the others it uses, so they can call each other and themselves
+fn hoist(block: &[Box<Expr>], scopes: &mut Vec<HashMap<String, DataType>>) {
+    let mut made: Vec<(Rc<Definition>, DataType)> = Vec::new();
+    for definition in members(block) {
+        if made.iter().any(|(other, _)| other.name == definition.name) {
+            continue;
+        }
+        let value = make_closure(&definition, scopes);
+        made.push((definition, value));
+    }
+    for (definition, value) in &made {
+        scopes[1].insert(definition.name.clone(), value.clone());
+    }
+    for (definition, value) in &made {
+        let names = uses(definition);
+        let closure = value.store.function.as_ref().unwrap();
+        for (other, other_value) in &made {
+            if names.contains(&other.name) {
+                closure.captured.borrow_mut().insert(other.name.clone(), other_value.clone());
+            }
+        }
+    }
+}
+
+// How many calls deep the code that's running is, 0 outside of functions
+fn call_depth(scopes: &[HashMap<String, DataType>]) -> usize {
+    match scopes.get(1).and_then(|scope| scope.get(FRAME)).and_then(|depth| depth.store.integer) {
+        Some(depth) => {return depth as usize;}
+        None => {return 0;}
+    }
+}
+
+// Parameters are `name` or `name = default`
+fn parameters(expr: &Definition) -> Vec<(String, Option<&Expr>)> {
+    let mut parameters: Vec<(String, Option<&Expr>)> = Vec::new();
+    for parameter in &expr.arguments {
+        match &**parameter {
+            Expr::Binary(BinaryExpr{operator: Operator::Equals, left, right}) => {
+                parameters.push((left.expand().unwrap().value, Some(&**right)));
+            }
+            _ => {parameters.push((parameter.expand().unwrap().value, None));}
+        }
+    }
+    return parameters;
+}
+
+// Defaults for the parameters that weren't given are worked out inside the function, so they can use the ones before them
+fn bind_defaults(expr: &Definition, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(), Flow> {
+    for (name, default) in parameters(expr) {
+        if scopes[1].contains_key(&name) {
+            continue;
+        }
+        match default {
+            Some(default) => {
+                let output = calculate_bexpr(default, scopes, functions)?.unwrap_or(DataType::new());
+                scopes.last_mut().unwrap().insert(name, output);
+            }
+            None => {return Err(Flow::Error(format!("INVALID ARGUMENTS: {} is missing a value for {}", function_name(expr), name)));}
+        }
+    }
+    return Ok(());
+}
+
+fn parameters_named(expr: &Definition, name: &str) -> bool {
+    if expr.rest.as_deref() == Some(name) {
+        return true;
+    }
+    return expr.arguments.iter().any(|parameter| {
+        match &**parameter {
+            Expr::Binary(BinaryExpr{left, ..}) => {return left.expand().unwrap().value == name;}
+            _ => {return parameter.expand().unwrap().value == name;}
+        }
+    });
+}
+
+fn function_name(expr: &Definition) -> String {
+    if expr.name.is_empty() {
+        return "this function".to_string();
+    }
+    return short_name(&expr.name).to_string();
+}
+
+// A function value keeps a copy of every local variable its body uses from the scope it was made in,
+// taken when it's made, so later changes to them in that scope aren't seen by it.
+// Globals stay shared since they are visible from everywhere anyway
+fn make_closure(expr: &Rc<Definition>, scopes: &mut Vec<HashMap<String, DataType>>) -> DataType {
+    let names = uses(expr);
+    let mut captured: HashMap<String, DataType> = HashMap::new();
+    for name in names {
+        match scopes.iter().rposition(|scope| scope.contains_key(&name)) {
+            Some(0) => {}
+            Some(index) => {
+                let value = scopes[index][&name].clone();
+                captured.insert(name, value);
+            }
+            None => {}
+        }
+    }
+    return DataType::function(Closure{definition: Rc::clone(expr), captured: RefCell::new(captured)});
+}
+
+// Every name the body and the parameter defaults use
+fn uses(expr: &Definition) -> HashSet<String> {
+    let mut names: HashSet<String> = HashSet::new();
+    for line in &expr.block {
+        used_names(line, &mut names);
+    }
+    for parameter in &expr.arguments {
+        match &**parameter {
+            Expr::Binary(BinaryExpr{operator: Operator::Equals, right, ..}) => {used_names(right, &mut names);}
+            _ => {}
+        }
+    }
+    return names;
+}
+
+// `f = fn(...) { f(...) }` inside of a function: f didn't exist yet when the closure copied the variables it uses,
+// so it's given itself under that name
+fn bind_self(expr: &Definition, name: &str, value: &DataType, scopes: &[HashMap<String, DataType>]) {
+    let Some(closure) = &value.store.function else { return; };
+    match scopes.iter().rposition(|scope| scope.contains_key(name)) {
+        Some(index) if index > 0 && !matches!(scopes[index][name].kind, Literal::Variable) => {}
+        _ => {return;}
+    }
+    let mut names: HashSet<String> = HashSet::new();
+    for line in &expr.block {
+        used_names(line, &mut names);
+    }
+    if names.contains(name) {
+        closure.captured.borrow_mut().insert(name.to_string(), value.clone());
+    }
+}
+
+fn used_names(expr: &Expr, names: &mut HashSet<String>) {
+    match expr {
+        Expr::Literal(lit) => {
+            match lit.kind {
+                Literal::Variable => {names.insert(lit.value.clone());}
+                Literal::Array => {
+                    for value in lit.store.array.as_ref().unwrap().iter() {
+                        used_names(value, names);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Expr::Unary(x) => {used_names(&x.value, names);}
+        Expr::Binary(x) => {
+            used_names(&x.left, names);
+            used_names(&x.right, names);
+        }
+        Expr::Block(x) => {
+            for line in x.conditions.iter().chain(x.block.iter()) {
+                used_names(line, names);
+            }
+        }
+        Expr::Function(x) => {
+            if matches!(x.kind, FunctionType::Defined) {
+                names.insert(x.name.clone());
+            }
+            for argument in &x.arguments {
+                used_names(argument, names);
+            }
+        }
+        Expr::Definition(x) => {
+            for line in &x.block {
+                used_names(line, names);
+            }
+        }
+        Expr::Nil => {}
+    }
+}
+
+pub fn run_return(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Flow, Flow> {
+    if expr.block.len() == 0 {
+        return Ok(Flow::Return(DataType::new()));
+    }
+    match &*expr.block[0] {
+        Expr::Function(call) if matches!(call.kind, FunctionType::Defined | FunctionType::Call) && call_depth(scopes) > 0 => {
+            let (closure, arguments) = callee(call, scopes, functions)?;
+            let values = bind_arguments(&closure.definition, arguments, scopes, functions)?;
+            return Ok(Flow::TailCall(closure, values));
+        }
+        _ => {}
+    }
+    let output = calculate_bexpr(&expr.block[0], scopes, functions)?.unwrap_or(DataType::new());
+    return Ok(Flow::Return(output));
+}
+
+// Runs the first branch whose condition holds, its trailing expression is the value of the whole chain
+pub fn run_if_chain(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    for branch in &expr.block {
+        let Expr::Block(branch) = &**branch else { continue; };
+        match branch.kind {
+            BlockType::Else => {}
+            _ => {
+                if branch.conditions.len() != 1 {
+                    return Err(Flow::Error("Conditions to this statement are invalid".to_string())); 
+                }
+                let condition = run_condition(&branch.conditions[0], scopes, functions)?;
+                if !condition {
+                    continue;
+                }
+            }
+        }
+        scopes.push(HashMap::new());
+        let output = run_branch(&branch.block, scopes, functions);
+        scopes.pop();
+        return output;
+    }
+    return Ok(None);
+}
+
+// Runs the try block, if it fails with an error the catch block runs instead with the message under the catch's name.
+// `exit` and the other ways of leaving a block aren't errors and pass straight through
+pub fn run_try(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let depth = scopes.len();
     scopes.push(HashMap::new());
+    let output = run_branch(&expr.block, scopes, functions);
+    // Blocks the error came out of don't always pop their scopes on the way
+    scopes.truncate(depth);
+    // `return f(...)` can't leave the try as a tail call, the errors it runs into still have to be caught here
+    let output = match output {
+        Err(Flow::TailCall(closure, values)) => {
+            match call_with(&closure, values, scopes, functions) {
+                Ok(value) => Err(Flow::Return(value.unwrap_or(DataType::new()))),
+                Err(flow) => Err(flow),
+            }
+        }
+        output => output,
+    };
+    let message = match output {
+        Err(Flow::Error(message)) => message,
+        output => {return output;}
+    };
+    let Some(Expr::Block(catch)) = expr.conditions.first().map(|catch| &**catch) else { return Ok(None); };
+    let mut scope: HashMap<String, DataType> = HashMap::new();
+    match &catch.label {
+        Some(name) => {scope.insert(name.clone(), DataType::string(message));}
+        None => {}
+    }
+    scopes.push(scope);
+    let output = run_branch(&catch.block, scopes, functions);
+    scopes.pop();
+    return output;
+}
+
+fn run_branch(block: &[Box<Expr>], scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    let Some((last, rest)) = block.split_last() else { return Ok(None); };
+    match run(rest, scopes, functions) {
+        Flow::Normal => {}
+        flow => {return Err(flow);}
+    }
+    match &**last {
+        Expr::Literal(..) | Expr::Block(Block{kind: BlockType::IfChain | BlockType::Match | BlockType::Conditional | BlockType::Try | BlockType::FormatedString, ..}) => {
+            return calculate_bexpr(last, scopes, functions);
+        }
+        Expr::Binary(BinaryExpr{operator, ..}) if !matches!(operator, Operator::Equals) => {
+            return calculate_bexpr(last, scopes, functions);
+        }
+        Expr::Function(Function{kind, ..}) if !matches!(kind, FunctionType::Print) => {
+            return calculate_bexpr(last, scopes, functions);
+        }
+        Expr::Unary(UnaryExpr{operator, ..}) if !matches!(operator, Operator::Plus | Operator::Minus) => {
+            return calculate_bexpr(last, scopes, functions);
+        }
+        Expr::Definition(definition) if definition.name.is_empty() => {
+            return calculate_bexpr(last, scopes, functions);
+        }
+        _ => {}
+    }
+    match run(std::slice::from_ref(last), scopes, functions) {
+        Flow::Normal => {return Ok(None);}
+        flow => {return Err(flow);}
+    }
+}
+
+// `condition ? then : otherwise` only evaluates the side it picks
+pub fn run_conditional(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
+    if run_condition(&expr.conditions[0], scopes, functions)? {
+        return calculate_bexpr(&expr.block[0], scopes, functions);
+    }
+    return calculate_bexpr(&expr.block[1], scopes, functions);
+}
+
+// Runs the first arm with a matching pattern, names bound by the pattern only live inside that arm
+pub fn run_match(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Option<DataType>, Flow> {
     if expr.conditions.len() != 1 {
-       return Err("Conditions to this statement are invalid".to_string()); 
+        return Err(Flow::Error("BAD CONDITIONS: match takes exactly one value".to_string()));
+    }
+    let value = calculate_bexpr(&expr.conditions[0], scopes, functions)?.unwrap_or(DataType::new());
+    for arm in &expr.block {
+        let Expr::Block(arm) = &**arm else { continue; };
+        for pattern in &arm.conditions {
+            let mut bindings: HashMap<String, DataType> = HashMap::new();
+            if !match_pattern(pattern, &value, &mut bindings, scopes, functions)? {
+                continue;
+            }
+            scopes.push(bindings);
+            let output = run_branch(&arm.block, scopes, functions);
+            scopes.pop();
+            return output;
+        }
     }
+    eprintln!("MATCH WARNING: no arm matched {}", value.value);
+    return Ok(None);
+}
 
-    let condition = calculate_bexpr(&expr.conditions[0], scopes, functions).unwrap().store.bool.unwrap();
-    let mut output: Option<DataType> = None;
-    
-    if condition {
-        output = run(&expr.block, scopes, functions);
+// `_` matches anything, a bare name matches anything and binds it, `a..b` matches integers from a up to b, arrays match element by element
+fn match_pattern(pattern: &Expr, value: &DataType, bindings: &mut HashMap<String, DataType>, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<bool, Flow> {
+    match pattern {
+        Expr::Literal(DataType{kind: Literal::Variable, value: name, ..}) => {
+            if name != "_" {
+                bindings.insert(name.clone(), value.clone());
+            }
+            return Ok(true);
+        }
+        Expr::Literal(DataType{kind: Literal::Array, store, ..}) => {
+            let patterns = store.array.clone().unwrap_or_default();
+            let Literal::Array = value.kind else { return Ok(false); };
+            let values = value.store.array.clone().unwrap_or_default();
+            if patterns.len() != values.len() {
+                return Ok(false);
+            }
+            for (pattern, element) in patterns.iter().zip(values.iter()) {
+                if !match_pattern(pattern, &element.expand().unwrap_or(DataType::new()), bindings, scopes, functions)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        Expr::Binary(BinaryExpr{operator: Operator::Range, left, right}) => {
+            let start = calculate_bexpr(left, scopes, functions)?.unwrap_or(DataType::new());
+            let end = calculate_bexpr(right, scopes, functions)?.unwrap_or(DataType::new());
+            match (start.store.integer, end.store.integer, value.store.integer) {
+                (Some(start), Some(end), Some(integer)) => {return Ok(start <= integer && integer < end);}
+                (Some(_), Some(_), None) => {return Ok(false);}
+                _ => {return Err(Flow::Error(format!("MATCH ERROR: Range patterns need integer bounds, not {} and {}", type_name(&start.kind), type_name(&end.kind))));}
+            }
+        }
+        _ => {
+            let expected = calculate_bexpr(pattern, scopes, functions)?.unwrap_or(DataType::new());
+            match equals(expected, value.clone()) {
+                Some(output) => {return Ok(output.store.bool.unwrap());}
+                None => {return Ok(false);}
+            }
+        }
     }
-    scopes.pop();
-    return Ok((condition, output));
 }
 
-pub fn run_else(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Result<Option<DataType>, String> {
+pub fn run_for(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Flow {
     scopes.push(HashMap::new());
-    let output = run(&expr.block, scopes, functions);
+    let output = run_for_loop(expr, scopes, functions);
     scopes.pop();
-    return Ok(output); 
+    match output {
+        Ok(flow) | Err(flow) => {return flow;}
+    }
 }
 
-pub fn run_for(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Definition>) -> Result<Option<DataType>, String> {
-    scopes.push(HashMap::new());
+fn run_for_loop(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Flow, Flow> {
     let mut condition;
     if expr.conditions.len() == 1 {
-        condition = calculate_bexpr(&expr.conditions[0], scopes, functions).unwrap().store.bool.unwrap();
+        condition = run_condition(&expr.conditions[0], scopes, functions)?;
         while condition {
-            run(&expr.block, scopes, functions);
-            condition = calculate_bexpr(&expr.conditions[0], scopes, functions).unwrap().store.bool.unwrap();
-            if !condition {
-                break;
+            match run(&expr.block, scopes, functions) {
+                Flow::Break(target) if targets(&target, &expr.label) => {break;}
+                Flow::Continue(target) if targets(&target, &expr.label) => {}
+                Flow::Normal => {}
+                output => {return Ok(output);}
             }
+            condition = run_condition(&expr.conditions[0], scopes, functions)?;
         }
+        return Ok(Flow::Normal);
     } else if expr.conditions.len() != 3 {
-       return Err("BAD CONDITIONS".to_string()); 
+       return Err(Flow::Error("BAD CONDITIONS".to_string())); 
     } 
 
-    let iterator_key: String;
-    let iterator_updater = &expr.conditions[2];
-
-    match *expr.conditions[0].clone()  {
+    // The iterator always lives in the loop's own scope, even if an outer variable shares its name
+    match &*expr.conditions[0] {
         Expr::Binary(name_expr) => {
             match name_expr.operator {
                 Operator::Equals => {
-                    iterator_key = name_expr.left.expand().expect("Where did the name go").value;
-                    let output = calculate_bexpr(&name_expr.right, scopes, functions).unwrap();
-                    set_into_current_scope(scopes, scopes.len()-1, iterator_key.clone(), output);
+                    let iterator_key = name_expr.left.expand().expect("Where did the name go").value;
+                    let output = calculate_bexpr(&name_expr.right, scopes, functions)?.unwrap();
+                    set_into_current_scope(scopes, scopes.len()-1, iterator_key, output);
                 }
                 _ => {
-                    return Err("BAD ITERATOR".to_string());
+                    return Err(Flow::Error("BAD ITERATOR".to_string()));
                 }
             }
         }
         _ => {
-            return Err("BAD ITERATOR".to_string());
+            return Err(Flow::Error("BAD ITERATOR".to_string()));
         }
     }
 
-    condition = calculate_bexpr(&expr.conditions[1], scopes, functions).unwrap().store.bool.unwrap();
+    condition = run_condition(&expr.conditions[1], scopes, functions)?;
 
     while condition {
-        let block_output = run(&expr.block, scopes, functions);
+        match run(&expr.block, scopes, functions) {
+            Flow::Break(target) if targets(&target, &expr.label) => {break;}
+            Flow::Continue(target) if targets(&target, &expr.label) => {}
+            Flow::Normal => {}
+            output => {return Ok(output);}
+        }
 
-        match block_output {
-            Some(..) => {return Ok(block_output);}
-            _ => {}
+        match run(&expr.conditions[2..], scopes, functions) {
+            Flow::Normal => {}
+            output => {return Ok(output);}
         }
 
-        let output = calculate_unexpr(&iterator_updater, scopes).unwrap();
-        set_into_current_scope(scopes, scopes.len()-1, iterator_key.clone(), output);
+        condition = run_condition(&expr.conditions[1], scopes, functions)?;
+    }
+    return Ok(Flow::Normal);
+}
 
-        condition = calculate_bexpr(&expr.conditions[1], scopes, functions).unwrap().store.bool.unwrap();
-        if !condition {
-            break;
+pub fn run_for_in(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Flow {
+    scopes.push(HashMap::new());
+    let output = run_for_in_loop(expr, scopes, functions);
+    scopes.pop();
+    match output {
+        Ok(flow) | Err(flow) => {return flow;}
+    }
+}
+
+// Loops over array elements, lines of a string or a `start..end` range, with an optional index variable
+fn run_for_in_loop(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Flow, Flow> {
+    let names = expr.conditions[0].expand().unwrap().store.array.unwrap();
+    if expr.conditions.len() != 2 || names.len() == 0 || names.len() > 2 {
+        return Err(Flow::Error("BAD CONDITIONS: Expected `for value in collection` or `for index, value in collection`".to_string()));
+    }
+    let value_key = names[names.len()-1].expand().unwrap().value;
+    let mut index_key: Option<String> = None;
+    if names.len() == 2 {
+        index_key = Some(names[0].expand().unwrap().value);
+    }
+
+    let mut values: Vec<DataType> = Vec::new();
+    let mut bounds: Option<(i32, i32)> = None;
+    match &*expr.conditions[1] {
+        // Ranges are counted through directly instead of being built into an array first
+        Expr::Binary(BinaryExpr{operator: Operator::Range, left, right}) => {
+            let start = calculate_bexpr(&left, scopes, functions)?.unwrap_or(DataType::new()).store.integer;
+            let end = calculate_bexpr(&right, scopes, functions)?.unwrap_or(DataType::new()).store.integer;
+            match (start, end) {
+                (Some(start), Some(end)) => {bounds = Some((start, end));}
+                _ => {return Err(Flow::Error("BAD CONDITIONS: Range bounds have to be integers".to_string()));}
+            }
+        }
+        _ => {
+            // Something without a value, like an if without a matching branch, is nil and can't be looped over
+            let collection = calculate_bexpr(&expr.conditions[1], scopes, functions)?.unwrap_or(DataType::new());
+            match collection.kind {
+                Literal::Array => {
+                    for value in collection.store.array.unwrap().iter() {
+                        values.push(value.expand().unwrap_or(DataType::new()));
+                    }
+                }
+                Literal::String => {
+                    for line in collection.value.lines() {
+                        values.push(DataType::string(line.to_string()));
+                    }
+                }
+                _ => {
+                    return Err(Flow::Error(format!("BAD CONDITIONS: Values of type {} can't be looped over", type_name(&collection.kind))));
+                }
+            }
         }
     }
-    scopes.pop();
-    return Ok(None);
+
+    // Counted in i64 so a range over the whole int range doesn't overflow
+    let length: i64;
+    match bounds {
+        Some((start, end)) => {length = (end as i64 - start as i64).max(0);}
+        None => {length = values.len() as i64;}
+    }
+
+    for i in 0..length {
+        let value: DataType;
+        match bounds {
+            Some((start, _)) => {value = DataType::int((start as i64 + i) as i32);}
+            None => {value = values[i as usize].clone();}
+        }
+        set_into_current_scope(scopes, scopes.len()-1, value_key.clone(), value);
+        match &index_key {
+            Some(key) => {set_into_current_scope(scopes, scopes.len()-1, key.clone(), DataType::int(i as i32));}
+            None => {}
+        }
+        match run(&expr.block, scopes, functions) {
+            Flow::Break(target) if targets(&target, &expr.label) => {break;}
+            Flow::Continue(target) if targets(&target, &expr.label) => {}
+            Flow::Normal => {}
+            output => {return Ok(output);}
+        }
+    }
+    return Ok(Flow::Normal);
+}
+
+// `while cond { }` checks its condition before every pass, `loop { }` only stops on break or return
+pub fn run_while(expr: &Block, scopes: &mut Vec<HashMap<String, DataType>>, functions: &mut HashMap<String, Rc<Definition>>) -> Flow {
+    loop {
+        match expr.kind {
+            BlockType::While => {
+                if expr.conditions.len() != 1 {
+                    return Flow::Error("BAD CONDITIONS: while takes exactly one condition".to_string());
+                }
+                match run_condition(&expr.conditions[0], scopes, functions) {
+                    Ok(condition) => {
+                        if !condition {
+                            break;
+                        }
+                    }
+                    Err(flow) => {return flow;}
+                }
+            }
+            _ => {}
+        }
+        scopes.push(HashMap::new());
+        let output = run(&expr.block, scopes, functions);
+        scopes.pop();
+        match output {
+            Flow::Break(target) if targets(&target, &expr.label) => {break;}
+            Flow::Continue(target) if targets(&target, &expr.label) => {}
+            Flow::Normal => {}
+            output => {return output;}
+        }
+    }
+    return Flow::Normal;
+}
+
+// An unlabeled break or continue belongs to the innermost loop
+fn targets(target: &Option<String>, label: &Option<String>) -> bool {
+    match target {
+        None => {return true;}
+        Some(..) => {return target == label;}
+    }
 }
 
-pub fn import(expr: &Block, functions: &mut HashMap<String, Definition>) -> Result<(), String> {
+pub fn import(expr: &Block, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(), Flow> {
     for lib in &expr.block {
-        let _ = std(functions, lib.expand().unwrap().value.as_str());
+        let target = lib.expand().unwrap().value;
+        let module = module(&target, functions)?;
+        let exports: Vec<String> = module.exports.keys().cloned().collect();
+        for (name, export) in bindings(expr, &target, &exports)? {
+            functions.insert(name, Rc::clone(&module.exports[&export]));
+        }
+        if expr.conditions.is_empty() {
+            name_module(namespace(expr, &target), &module);
+        }
     } 
     return Ok(())
 }
 
 
+// A function call's own scope holds how deep the call is under this key, `fn` can't be a variable name so it never clashes
+const FRAME: &str = "fn";
+
+// Assigning looks for the variable from the innermost scope down to the function's own one,
+// only code outside of functions or a `global` declaration reaches the global scope
 pub fn set_into_scope(scopes: &mut Vec<HashMap<String, DataType>>, index: usize, name: &str, value: DataType) {
-    for scope in &mut *scopes {
-        match scope.get(name) {
+    for i in (0..scopes.len()).rev() {
+        match scopes[i].get(name) {
+            Some(DataType{kind: Literal::Variable, ..}) => {
+                scopes[0].insert(name.to_string(), value);
+                return;
+            }
             Some(..) => {
-                scope.insert(name.to_string(), value);
+                scopes[i].insert(name.to_string(), value);
                 return;
             }
-            _ => {}
+            None => {}
+        }
+        if scopes[i].contains_key(FRAME) {
+            break;
         }
     }
     scopes[index].insert(name.to_string(), value);
@@ -466,9 +1391,20 @@ pub fn get_from_scope(scopes: &mut Vec<HashMap<String, DataType>>, name: &str) -
         let var = scopes[scopes.len() - i - 1].get(name);
         match var {
             None => {} 
+            // Declared with `global`
+            Some(DataType{kind: Literal::Variable, ..}) => {
+                match scopes[0].get(name) {
+                    Some(value) => {return Ok(Some(value.clone()));}
+                    None => {break;}
+                }
+            }
             _ => {return Ok(var.cloned())}
         }
     } 
+    match module_variable(name) {
+        Some(value) => {return Ok(Some(value));}
+        None => {}
+    }
     return Err(format!("VARIABLE NOT FOUND: {} wasn't found", name));
 }
 
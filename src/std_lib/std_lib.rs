@@ -1,17 +1,142 @@
-use std::collections::HashMap;
-use std::fs::File;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use crate::parsing::lexer::{Token, tokenize};
-use crate::parsing::parser::{DataType, Definition, parse};
-use crate::runtime::runtime::run;
+use crate::parsing::parser::{Block, DataType, Definition, Expr, FunctionType, Literal, ModuleScope, named_builtin, parse};
+use crate::parsing::resolver::{bind, names, resolve, visit, visit_mut, visit_scoped_mut};
+use crate::runtime::runtime::{Flow, run};
 use home::home_dir;
 
-pub fn std(functions: &mut HashMap<String, Definition>, name: &str) -> std::io::Result<()> {
-    let path = home_dir().unwrap().display().to_string();
-    let mut file = File::open(format!("{}/.rash/std/{}.rash", path, name)).expect(format!("IMPORT NOT FOUND: Import {} wasn't found.", name).as_str());
+// A module that has been run, importing it again only hands out its functions and variables
+pub struct Module {
+    pub exports: HashMap<String, Rc<Definition>>,
+    pub scope: Rc<ModuleScope>,
+}
+
+// A module's globals know which module they belong to under this key, `import` can't be a variable name
+pub const MODULE: &str = "import";
+
+struct Loader {
+    // The directory of every file being run, relative imports start from the last one
+    directories: Vec<PathBuf>,
+    // Files that are still running their imports, reaching one of them again is a cycle
+    loading: Vec<PathBuf>,
+    modules: HashMap<PathBuf, Rc<Module>>,
+    // What `namespace.name` reads from, the alias of an import or the module's own name
+    namespaces: HashMap<String, Rc<ModuleScope>>,
+    // The globals of every file by its path, the script's included, for when a call switches away from them
+    scopes: HashMap<String, Rc<ModuleScope>>,
+}
+
+// The std modules are built into the binary, so they're there even without ~/.rash and always match the interpreter
+const EMBEDDED: [(&str, &str); 4] = [
+    ("algorithms", include_str!("../../std/algorithms.rash")),
+    ("cmd", include_str!("../../std/cmd.rash")),
+    ("crypto", include_str!("../../std/crypto.rash")),
+    ("math", include_str!("../../std/math.rash")),
+];
+
+// Embedded modules get a path under here, it can't be a real directory
+const EMBEDDED_ROOT: &str = "<std>";
+
+thread_local! {
+    static LOADER: RefCell<Loader> = RefCell::new(Loader{directories: Vec::new(), loading: Vec::new(), modules: HashMap::new(), namespaces: HashMap::new(), scopes: HashMap::new()});
+}
+
+// Called with the script before it runs, its imports are relative to it. Its functions are tied to the scope it gets back
+pub fn enter(script: &Path) -> Rc<ModuleScope> {
+    let path = fs::canonicalize(script).unwrap_or(script.to_path_buf());
+    let scope = Rc::new(ModuleScope{path: path.display().to_string(), globals: RefCell::new(HashMap::new())});
+    LOADER.with(|loader| {
+        let mut loader = loader.borrow_mut();
+        loader.directories.push(path.parent().map(|dir| dir.to_path_buf()).unwrap_or_default());
+        loader.loading.push(path);
+        loader.scopes.insert(scope.path.clone(), Rc::clone(&scope));
+    });
+    return scope;
+}
+
+// Where a plain module name is looked for, in order: next to the importing file, in the project's rash_modules
+// and in every directory listed in RASH_PATH. The std directory comes after the embedded std
+fn search_path() -> Vec<PathBuf> {
+    let mut directories: Vec<PathBuf> = Vec::new();
+    LOADER.with(|loader| {
+        let loader = loader.borrow();
+        match loader.directories.last() {
+            Some(directory) => {directories.push(directory.clone());}
+            None => {}
+        }
+        // The project is wherever the script being run lives
+        match loader.directories.first() {
+            Some(directory) => {directories.push(directory.join("rash_modules"));}
+            None => {}
+        }
+    });
+    match env::var_os("RASH_PATH") {
+        Some(paths) => {
+            for directory in env::split_paths(&paths) {
+                if !directory.as_os_str().is_empty() {
+                    directories.push(directory);
+                }
+            }
+        }
+        None => {}
+    }
+    return directories;
+}
+
+// Quoted paths and anything with a slash or a .rash in it are relative to the importing file, plain names go through the search path,
+// then the embedded std and then ~/.rash/std, so a copy of a std module left there from an older rash is never used
+pub fn locate(target: &str) -> Result<PathBuf, Flow> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    // Where the embedded std goes in the list of places that were searched
+    let mut builtin: usize = 0;
+    let plain = !target.contains('/') && !target.ends_with(".rash");
+    let embedded = plain && EMBEDDED.iter().any(|(name, _)| *name == target);
+    if !plain {
+        let directory = LOADER.with(|loader| loader.borrow().directories.last().cloned().unwrap_or_default());
+        candidates.push(directory.join(target));
+    } else {
+        for directory in search_path() {
+            candidates.push(directory.join(format!("{}.rash", target)));
+        }
+        builtin = candidates.len();
+        match home_dir() {
+            Some(home) if !embedded => {candidates.push(home.join(".rash").join("std").join(format!("{}.rash", target)));}
+            _ => {}
+        }
+    }
+    for candidate in &candidates {
+        match fs::canonicalize(candidate) {
+            Ok(path) if path.is_file() => {return Ok(path);}
+            _ => {}
+        }
+    }
+    if embedded {
+        return Ok(Path::new(EMBEDDED_ROOT).join(format!("{}.rash", target)));
+    }
+    let mut searched: Vec<String> = candidates.iter().map(|candidate| candidate.display().to_string()).collect();
+    if plain {
+        searched.insert(builtin, "the built in std".to_string());
+    }
+    return Err(Flow::Error(format!("IMPORT NOT FOUND: Import {} wasn't found, looked in {}", target, searched.join(", "))));
+}
+
+// Reads and parses a module without running it
+pub fn read(path: &Path) -> Result<Vec<Box<Expr>>, Flow> {
     let mut script = String::new();
-    file.read_to_string(&mut script)?;
+    match EMBEDDED.iter().find(|(name, _)| Path::new(EMBEDDED_ROOT).join(format!("{}.rash", name)) == path) {
+        Some((_, source)) => {script.push_str(source);}
+        None => {
+            let mut file = File::open(path).map_err(|_| Flow::Error(format!("IMPORT NOT FOUND: Import {} wasn't found.", path.display())))?;
+            file.read_to_string(&mut script).map_err(|e| e.to_string())?;
+        }
+    }
 
     let mut tokens: Vec<Token> = Vec::new();
 
@@ -22,10 +147,206 @@ pub fn std(functions: &mut HashMap<String, Definition>, name: &str) -> std::io::
         }
     }
 
-    let tree = parse(tokens);
+    return Ok(parse(tokens));
+}
+
+// The top level functions other files can import, every one of them unless some are marked with `export`
+pub fn exports(tree: &[Box<Expr>]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut exported: Vec<String> = Vec::new();
+    for expr in tree {
+        match &**expr {
+            Expr::Definition(definition) if !definition.name.is_empty() => {
+                names.push(definition.name.clone());
+                if definition.export {
+                    exported.push(definition.name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    if exported.is_empty() {
+        return names;
+    }
+    return exported;
+}
+
+// What `namespace.name` refers to an import by, its alias or else the file name without .rash
+pub fn namespace(expr: &Block, target: &str) -> String {
+    match &expr.label {
+        Some(alias) => {return alias.clone();}
+        None => {return Path::new(target).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or(target.to_string());}
+    }
+}
+
+// The names an import makes callable, each with the exported function it points to.
+// A plain import makes them callable as `name` and `module.name`, `as alias` only as `alias.name`,
+// `from module import name` only takes the names it lists
+pub fn bindings(expr: &Block, target: &str, exports: &[String]) -> Result<Vec<(String, String)>, Flow> {
+    let mut names: Vec<(String, String)> = Vec::new();
+    if !expr.conditions.is_empty() {
+        for picked in &expr.conditions {
+            let name = picked.expand().unwrap().value;
+            if !exports.contains(&name) {
+                return Err(Flow::Error(format!("IMPORT ERROR: {} doesn't export {}", target, name)));
+            }
+            names.push((name.clone(), name));
+        }
+        return Ok(names);
+    }
+    let namespace = namespace(expr, target);
+    for name in exports {
+        match &expr.label {
+            Some(_) => {}
+            None => {names.push((name.clone(), name.clone()));}
+        }
+        names.push((format!("{}.{}", namespace, name), name.clone()));
+    }
+    return Ok(names);
+}
+
+// Loads, checks and runs a module the first time it's imported, later imports get the same one back
+pub fn module(target: &str, functions: &mut HashMap<String, Rc<Definition>>) -> Result<Rc<Module>, Flow> {
+    let path = locate(target)?;
+    match LOADER.with(|loader| loader.borrow().modules.get(&path).cloned()) {
+        Some(module) => {return Ok(module);}
+        None => {}
+    }
+    let cycle = LOADER.with(|loader| {
+        let loader = loader.borrow();
+        let start = loader.loading.iter().position(|file| *file == path)?;
+        let mut files: Vec<String> = loader.loading[start..].iter().map(|file| file.display().to_string()).collect();
+        files.push(path.display().to_string());
+        return Some(files.join(" -> "));
+    });
+    match cycle {
+        Some(files) => {return Err(Flow::Error(format!("IMPORT CYCLE: {}", files)));}
+        None => {}
+    }
+
+    let mut tree = read(&path)?;
+    let public = exports(&tree);
+    let scope = Rc::new(ModuleScope{path: path.display().to_string(), globals: RefCell::new(HashMap::new())});
+    qualify(&mut tree, &path);
+    tie(&mut tree, &scope);
 
-    let mut scopes: Vec<HashMap<String, DataType>> = Vec::new();
-    run(&tree, &mut scopes, functions);
+    LOADER.with(|loader| {
+        let mut loader = loader.borrow_mut();
+        loader.directories.push(path.parent().unwrap().to_path_buf());
+        loader.loading.push(path.clone());
+        loader.scopes.insert(scope.path.clone(), Rc::clone(&scope));
+    });
+    let output = run_module(&mut tree, &scope, functions);
+    LOADER.with(|loader| {
+        let mut loader = loader.borrow_mut();
+        loader.directories.pop();
+        loader.loading.pop();
+    });
+    output?;
+
+    let mut module = Module{exports: HashMap::new(), scope};
+    for name in public {
+        match functions.get(&format!("{}::{}", path.display(), name)) {
+            Some(definition) => {module.exports.insert(name, Rc::clone(definition));}
+            None => {}
+        }
+    }
+    let module = Rc::new(module);
+    LOADER.with(|loader| loader.borrow_mut().modules.insert(path, Rc::clone(&module)));
+    return Ok(module);
+}
+
+// The module's top level runs once, whatever it leaves in its global scope stays there for its functions and importers
+fn run_module(tree: &mut [Box<Expr>], scope: &Rc<ModuleScope>, functions: &mut HashMap<String, Rc<Definition>>) -> Result<(), Flow> {
+    let errors = resolve(tree, functions);
+    if !errors.is_empty() {
+        return Err(Flow::Error(errors.join("\n")));
+    }
+
+    let mut globals: HashMap<String, DataType> = HashMap::new();
+    globals.insert(MODULE.to_string(), DataType::string(scope.path.clone()));
+    let mut scopes: Vec<HashMap<String, DataType>> = vec![globals];
+    let output = run(tree, &mut scopes, functions);
+    *scope.globals.borrow_mut() = scopes.swap_remove(0);
+    match output {
+        Flow::Exit(code) => {return Err(Flow::Exit(code));}
+        Flow::Error(error) => {return Err(Flow::Error(error));}
+        _ => {}
+    }
 
     return Ok(());
 }
+
+// Every function a module defines at its top level is registered as `path::name`, so it never clashes with the importer's own.
+// The module's calls and references to them are renamed to match, unless the name is also used for a variable
+// or a function defined inside of another one has it there
+fn qualify(tree: &mut Vec<Box<Expr>>, path: &Path) {
+    let mut bound: HashSet<String> = HashSet::new();
+    for expr in tree.iter() {
+        visit(expr, &mut |expr| {
+            bind(expr, &mut bound);
+        });
+    }
+    let qualified = |name: &str| format!("{}::{}", path.display(), name);
+    // Only the first namespace is the file's, the rest belong to the functions the expression is inside of
+    let top = |name: &str, namespaces: &[HashSet<String>]| namespaces[0].contains(name) && !namespaces[1..].iter().any(|names| names.contains(name));
+    let mut namespaces: Vec<HashSet<String>> = vec![names(tree)];
+    for expr in tree.iter_mut() {
+        visit_scoped_mut(expr, &mut namespaces, names, &mut |expr, namespaces| {
+            match expr {
+                Expr::Definition(definition) if namespaces.len() == 1 && top(&definition.name, namespaces) => {
+                    let definition = Rc::make_mut(definition);
+                    definition.name = qualified(&definition.name);
+                }
+                Expr::Function(call) if (matches!(call.kind, FunctionType::Defined) || named_builtin(call)) && top(&call.name, namespaces) => {
+                    call.name = qualified(&call.name);
+                    call.kind = FunctionType::Defined;
+                }
+                Expr::Literal(DataType{kind: Literal::Variable, value, ..}) if top(value, namespaces) && !bound.contains(value) => {
+                    *value = qualified(value);
+                }
+                _ => {}
+            }
+        });
+    }
+}
+
+// Every function in a file, named or not, runs with the globals of that file wherever it's called from
+pub fn tie(tree: &mut [Box<Expr>], scope: &Rc<ModuleScope>) {
+    for expr in tree.iter_mut() {
+        visit_mut(expr, &mut |expr| {
+            match expr {
+                Expr::Definition(definition) => {Rc::make_mut(definition).module = Some(Rc::clone(scope));}
+                _ => {}
+            }
+        });
+    }
+}
+
+// The file a set of globals belongs to, they're kept in its scope while a call from another file runs
+pub fn owner(globals: &HashMap<String, DataType>) -> Rc<ModuleScope> {
+    let path = globals.get(MODULE).map(|path| path.value.clone()).unwrap_or_default();
+    match LOADER.with(|loader| loader.borrow().scopes.get(&path).cloned()) {
+        Some(scope) => {return scope;}
+        None => {return Rc::new(ModuleScope{path, globals: RefCell::new(HashMap::new())});}
+    }
+}
+
+// Makes the module's variables readable as `namespace.name`
+pub fn name_module(namespace: String, module: &Module) {
+    LOADER.with(|loader| loader.borrow_mut().namespaces.insert(namespace, Rc::clone(&module.scope)));
+}
+
+// `namespace.name` reads a top level variable of an imported module
+pub fn module_variable(name: &str) -> Option<DataType> {
+    let (namespace, variable) = name.rsplit_once('.')?;
+    if variable == MODULE {
+        return None;
+    }
+    return LOADER.with(|loader| {
+        let loader = loader.borrow();
+        let scope = loader.namespaces.get(namespace)?;
+        let value = scope.globals.borrow().get(variable).cloned();
+        return value;
+    });
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn arrays_slice_with_steps_and_clamp_their_bounds() {
+    let source = "arr = [1, 2, 3, 4, 5]\nprint(arr[1:3], arr[::-2], arr[10:], arr[-100:2])";
+    assert_eq!(output(source), "[ 2; 3; ]\n[ 5; 3; 1; ]\n[ ]\n[ 1; 2; ]\n");
+}
+
+#[test]
+fn a_zero_step_is_an_error() {
+    assert_eq!(error("arr = [1, 2]\nprint(arr[::0])"), "SLICE ERROR: The step of a slice can't be zero");
+}
+
+#[test]
+fn indexes_out_of_range_are_errors() {
+    assert_eq!(error("arr = [1, 2]\nprint(arr[-3])"), "INDEX ERROR: Index -3 is out of range for length 2");
+}
+
+#[test]
+fn push_pop_swap_and_delete_return_the_changed_array() {
+    let source = "print(push([1], 2, 3), pop([1, 2]), swap([1, 2], -1, 9), delete([1, 2, 3], 0))";
+    assert_eq!(output(source), "[ 1; 2; 3; ]\n[ 1; ]\n[ 1; 9; ]\n[ 2; 3; ]\n");
+}
+
+#[test]
+fn push_pop_swap_and_delete_report_bad_arguments() {
+    assert_eq!(error("print(push(5, 1))"), "TYPE ERROR: push needs an array, not int");
+    assert_eq!(error("print(pop(5))"), "TYPE ERROR: pop needs an array, not int");
+    assert_eq!(error("arr = [1]\nprint(swap(arr, 0))"), "INVALID ARGUMENTS: swap is missing a value");
+    assert_eq!(error("arr = [1]\nprint(swap(arr, 3, 0))"), "INDEX ERROR: Index 3 is out of range for length 1");
+    assert_eq!(error("print(delete([1, 2]))"), "INVALID ARGUMENTS: delete is missing a value");
+}
+
+#[test]
+fn changing_a_copy_leaves_the_original_alone() {
+    assert_eq!(output("a = [1, 2]\nb = push(a, 3)\nprint(a, b)"), "[ 1; 2; ]\n[ 1; 2; 3; ]\n");
+}
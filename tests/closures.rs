@@ -0,0 +1,32 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn functions_are_values() {
+    assert_eq!(output("double = fn(x) { x * 2 }\nsteps = [double, fn(x) { x - 1 }]\nprint(steps[1](double(3)))"), "5\n");
+    assert_eq!(output("fn named(x) { x + 1 }\ng = named\nprint(g(1), type(g))"), "2\nfunction\n");
+    assert_eq!(output("fn apply(f, x) { f(x) }\nprint(apply(fn(x) { x + 100 }, 1))"), "101\n");
+}
+
+#[test]
+fn closures_capture_their_defining_scope() {
+    assert_eq!(output("fn make_adder(n) {\n  return fn(x) { x + n }\n}\nadd5 = make_adder(5)\nprint(add5(1), make_adder(2)(3))"), "6\n5\n");
+    assert_eq!(output("n = 10\nfn outer() {\n  n = 1\n  return fn() { n }\n}\nprint(outer()())"), "1\n");
+}
+
+#[test]
+fn closures_keep_their_own_changes() {
+    assert_eq!(output("fn counter() {\n  count = 0\n  return fn() { count += 1; count }\n}\ntick = counter()\ntick()\nprint(tick())"), "2\n");
+    assert_eq!(output("fn maker() {\n  v = 1\n  bump = fn() { v += 1; v }\n  bump()\n  return v\n}\nprint(maker())"), "1\n");
+}
+
+#[test]
+fn closures_can_recurse_through_their_variable() {
+    assert_eq!(output("fn factorial(n) {\n  fact = fn(n) { n == 0 ? 1 : n * fact(n - 1) }\n  return fact(n)\n}\nprint(factorial(5))"), "120\n");
+}
+
+#[test]
+fn only_functions_can_be_called() {
+    assert_eq!(error("x = 5\nprint(x())"), "TYPE ERROR: int x can't be called");
+}
@@ -0,0 +1,66 @@
+// Runs rash scripts with the built interpreter in a directory of their own and collects what they print
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DIRECTORIES: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: i32,
+}
+
+// A fresh directory for one test, it's also HOME so nothing in the real ~/.rash gets picked up
+pub fn scratch() -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rash-test-{}-{}", std::process::id(), DIRECTORIES.fetch_add(1, Ordering::SeqCst)));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    return directory;
+}
+
+pub fn write(directory: &Path, name: &str, source: &str) {
+    let path = directory.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, source).unwrap();
+}
+
+pub fn run_in(directory: &Path, script: &str, variables: &[(&str, &str)]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_rash"))
+        .arg(directory.join(script))
+        .current_dir(directory)
+        .env("HOME", directory)
+        .env_remove("RASH_PATH")
+        .env_remove("RASH_MAX_DEPTH")
+        .envs(variables.iter().copied())
+        .output()
+        .unwrap();
+    return Output{
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        code: output.status.code().unwrap_or(-1),
+    };
+}
+
+pub fn run(source: &str) -> Output {
+    let directory = scratch();
+    write(&directory, "main.rash", source);
+    return run_in(&directory, "main.rash", &[]);
+}
+
+// What a script that has to succeed prints
+pub fn output(source: &str) -> String {
+    let output = run(source);
+    assert_eq!(output.code, 0, "the script failed with {}", output.stderr);
+    return output.stdout;
+}
+
+// The error a script that has to fail stops with
+pub fn error(source: &str) -> String {
+    let output = run(source);
+    assert_eq!(output.code, 1, "the script didn't fail, it printed {}", output.stdout);
+    return output.stderr.trim_end().to_string();
+}
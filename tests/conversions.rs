@@ -0,0 +1,28 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn int_parses_strings_in_any_base() {
+    assert_eq!(output("print(int(\" 42 \"), int(\"101\", 2), int(\"-0x1f\", 16), int(true), int(-3.9))"), "42\n5\n-31\n1\n-3\n");
+}
+
+#[test]
+fn int_reaches_both_ends_of_the_range() {
+    assert_eq!(output("print(int(\"-2147483648\"), int(\"2147483647\"), int(2147483647.5))"), "-2147483648\n2147483647\n2147483647\n");
+}
+
+#[test]
+fn int_rejects_values_that_do_not_fit() {
+    assert_eq!(error("print(int(\"2147483648\"))"), "CONVERSION ERROR: 2147483648 (string) can't be converted into an int");
+    assert_eq!(error("print(int(-30000000000.0))"), "CONVERSION ERROR: -30000000000.0 (float) can't be converted into an int");
+    assert_eq!(error("print(int(float(\"inf\")))"), "CONVERSION ERROR: inf (float) can't be converted into an int");
+    assert_eq!(error("print(int(float(\"nan\")))"), "CONVERSION ERROR: NaN (float) can't be converted into an int");
+    assert_eq!(error("print(int(\"--5\"))"), "CONVERSION ERROR: --5 (string) can't be converted into an int");
+}
+
+#[test]
+fn other_conversions() {
+    assert_eq!(output("print(float(3), float(\"2.5\"), string(12) + \"!\", type(string(1.5)), array(\"ab\"))"), "3.0\n2.5\n12!\nstring\n[ a; b; ]\n");
+    assert_eq!(error("print(float(\"x\"))"), "CONVERSION ERROR: x (string) can't be converted into a float");
+}
@@ -0,0 +1,15 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn division_rounds_ints_towards_zero_and_keeps_floats() {
+    assert_eq!(output("print(7 / 2, -7 / 2, 7.5 / 2, 1 / 4.0)"), "3\n-3\n3.75\n0.25\n");
+}
+
+#[test]
+fn dividing_by_any_zero_is_an_error() {
+    for source in ["print(1 / 0)", "print(1.5 / 0)", "print(1.5 / 0.0)", "print(1 / 0.0)", "print(5 % 0.0)", "print(7 ~/ 0)", "x = 2.0\nx /= 0.0"] {
+        assert_eq!(error(source), "MATH ERROR: Division by zero", "{}", source);
+    }
+}
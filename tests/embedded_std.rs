@@ -0,0 +1,35 @@
+mod common;
+
+use common::{output, run_in, scratch, write};
+
+#[test]
+fn std_modules_work_without_a_std_directory() {
+    assert_eq!(output("import math as m\nfrom algorithms import binary_search\nprint(m.pow(2, 8), binary_search([1, 3, 5], 3))"), "256\n1\n");
+}
+
+#[test]
+fn stale_copies_in_the_home_std_directory_are_ignored() {
+    let directory = scratch();
+    write(&directory, ".rash/std/math.rash", "fn pow(a, b) { \"stale\" }");
+    write(&directory, "main.rash", "import math\nprint(pow(2, 3))");
+    let output = run_in(&directory, "main.rash", &[]);
+    assert_eq!(output.stdout, "8\n", "{}", output.stderr);
+}
+
+#[test]
+fn other_modules_in_the_home_std_directory_still_load() {
+    let directory = scratch();
+    write(&directory, ".rash/std/extra.rash", "fn mine() { \"mine\" }");
+    write(&directory, "main.rash", "import extra\nprint(mine())");
+    let output = run_in(&directory, "main.rash", &[]);
+    assert_eq!(output.stdout, "mine\n", "{}", output.stderr);
+}
+
+#[test]
+fn project_modules_can_still_replace_std_ones() {
+    let directory = scratch();
+    write(&directory, "rash_modules/math.rash", "fn pow(a, b) { \"local\" }");
+    write(&directory, "main.rash", "import math\nprint(pow(2, 3))");
+    let output = run_in(&directory, "main.rash", &[]);
+    assert_eq!(output.stdout, "local\n", "{}", output.stderr);
+}
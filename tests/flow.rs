@@ -0,0 +1,27 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn exit_stops_the_script_with_its_code() {
+    let stopped = run("print(1)\nexit(3)\nprint(2)");
+    assert_eq!((stopped.stdout.as_str(), stopped.code), ("1\n", 3));
+    assert_eq!(run("fn f() { exit(4) }\nf()\nprint(1)").code, 4);
+    assert_eq!(run("exit()").code, 0);
+}
+
+#[test]
+fn errors_stop_the_script_on_stderr() {
+    let failed = run("print(\"a\")\nx = y\nprint(\"b\")");
+    assert_eq!((failed.stdout.as_str(), failed.stderr.as_str(), failed.code), ("a\n", "VARIABLE NOT FOUND: y wasn't found\n", 1));
+}
+
+#[test]
+fn return_leaves_every_block_around_it() {
+    assert_eq!(output("fn f() {\nfor x in [1, 2] {\nwhile true {\nif x == 1 { return x + 1 }\n}\n}\n}\nprint(f())"), "2\n");
+}
+
+#[test]
+fn a_block_ending_in_a_value_is_not_a_return() {
+    assert_eq!(output("fn f() {\nfor x in [1, 2] {\nif x == 1 { x }\n}\nreturn \"done\"\n}\nprint(f())"), "done\n");
+}
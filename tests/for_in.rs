@@ -0,0 +1,32 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn loops_over_array_elements_with_an_optional_index() {
+    assert_eq!(output("for x in [1, 2] {\nprint(x)\n}"), "1\n2\n");
+    assert_eq!(output("for i, x in [\"a\", \"b\"] {\nprint(i, x)\n}"), "0\na\n1\nb\n");
+}
+
+#[test]
+fn loops_over_ranges_without_building_them() {
+    assert_eq!(output("for x in 3..5 {\nprint(x)\n}\nfor x in 5..3 {\nprint(x)\n}"), "3\n4\n");
+    assert_eq!(output("for x in 2147483646..2147483647 {\nprint(x)\n}"), "2147483646\n");
+}
+
+#[test]
+fn loops_over_the_lines_of_command_output() {
+    assert_eq!(output("for line in c\"echo a; echo b\" {\nprint(line)\n}"), "a\nb\n");
+}
+
+#[test]
+fn the_loop_variable_stays_inside_of_the_loop() {
+    assert_eq!(error("for x in [1] {\n}\nprint(x)"), "VARIABLE NOT FOUND: x wasn't found");
+}
+
+#[test]
+fn collections_that_cant_be_looped_over_are_errors() {
+    assert_eq!(error("for x in 5 {\nprint(x)\n}"), "BAD CONDITIONS: Values of type int can't be looped over");
+    assert_eq!(error("for x in if false { [1] } {\nprint(x)\n}"), "BAD CONDITIONS: Values of type nil can't be looped over");
+    assert_eq!(error("for x in 0..(if false { 1 }) {\nprint(x)\n}"), "BAD CONDITIONS: Range bounds have to be integers");
+}
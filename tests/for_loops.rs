@@ -0,0 +1,21 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn any_statement_can_be_the_step() {
+    assert_eq!(output("for i = 0; i < 10; i += 3; {\nprint(i)\n}"), "0\n3\n6\n9\n");
+    assert_eq!(output("for i = 1; i < 20; i = i * 2; {\nprint(i)\n}"), "1\n2\n4\n8\n16\n");
+    assert_eq!(output("for i = 3; i > 0; --i; {\nprint(i)\n}"), "3\n2\n1\n");
+}
+
+#[test]
+fn leaving_a_loop_early_drops_its_scope() {
+    let source = "fn first() {\nfor i = 0; i < 5; ++i; {\nif i == 2 { return i }\n}\n}\n\
+                  print(first())\n\
+                  for i = 0; i < 5; ++i; {\nbreak\n}\n\
+                  total = 7\n\
+                  fn read() { total }\n\
+                  print(read())";
+    assert_eq!(output(source), "2\n7\n");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn map_filter_and_reduce() {
+    assert_eq!(output("a = [3, 1, 2]\nprint(map(a, fn(x) { x * 10 }), filter(a, fn(x) { x > 1 }), reduce(a, fn(t, x) { t + x }, 0))"), "[ 30; 10; 20; ]\n[ 3; 2; ]\n6\n");
+}
+
+#[test]
+fn sorting_leaves_the_original_alone() {
+    assert_eq!(output("a = [3, 1, 2]\nprint(sort(a), a, sort([\"b\", \"a\"]))"), "[ 1; 2; 3; ]\n[ 3; 1; 2; ]\n[ a; b; ]\n");
+    assert_eq!(output("print(sort_by([\"kiwi\", \"fig\", \"banana\"], fn(w) { len(w) }))"), "[ fig; kiwi; banana; ]\n");
+}
+
+#[test]
+fn searching() {
+    assert_eq!(output("a = [3, 1, 2]\nprint(find(a, fn(x) { x < 3 }), find(a, fn(x) { x > 9 }))"), "1\nnil\n");
+    assert_eq!(output("a = [3, 1, 2]\nprint(any(a, fn(x) { x > 2 }), all(a, fn(x) { x > 2 }), any([], fn(x) { true }), all([], fn(x) { false }))"), "true\nfalse\nfalse\ntrue\n");
+}
+
+#[test]
+fn reshaping() {
+    assert_eq!(output("print(zip([3, 1, 2], [\"x\", \"y\"]), enumerate([\"p\", \"q\"]))"), "[ [3, \"x\"]; [1, \"y\"]; ]\n[ [0, \"p\"]; [1, \"q\"]; ]\n");
+    assert_eq!(output("print(reverse([3, 1, 2]), unique([1, 2, 1, 3, 2]))"), "[ 2; 1; 3; ]\n[ 1; 2; 3; ]\n");
+}
+
+#[test]
+fn bad_arguments_are_reported() {
+    assert_eq!(error("print(map([1], 5))"), "TYPE ERROR: map needs a function, not int");
+    assert_eq!(error("print(map(3, fn(x) { x }))"), "TYPE ERROR: map needs an array, not int");
+    assert_eq!(error("print(sort([1, \"a\"]))"), "TYPE ERROR: string and int can't be sorted together");
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{error, output, run_in, scratch, write};
+
+#[test]
+fn functions_can_be_called_above_their_definition() {
+    assert_eq!(output("print(square(4))\nfn square(x) { x * x }"), "16\n");
+}
+
+#[test]
+fn nested_functions_run_inside_their_function() {
+    let source = "fn outer(n) {\n  print(inner(n))\n  fn inner(x) { x * 10 }\n  return inner(n + 1)\n}\nprint(outer(4))";
+    assert_eq!(output(source), "40\n50\n");
+}
+
+#[test]
+fn nested_functions_are_not_visible_outside() {
+    assert_eq!(error("fn outer() {\n  fn inner() { 1 }\n  return inner()\n}\nprint(outer())\ninner()"), "FUNCTION NOT FOUND: inner wasn't found");
+}
+
+#[test]
+fn nested_functions_call_each_other_and_themselves() {
+    let source = "fn parity(n) {\n  fn is_even(k) { k == 0 ? true : is_odd(k - 1) }\n  fn is_odd(k) { k == 0 ? false : is_even(k - 1) }\n  return is_even(n)\n}\nprint(parity(4), parity(7))";
+    assert_eq!(output(source), "true\nfalse\n");
+    let source = "fn fact(n) {\n  fn go(n, acc) {\n    if n == 0 { return acc }\n    return go(n - 1, acc * n)\n  }\n  return go(n, 1)\n}\nprint(fact(5))";
+    assert_eq!(output(source), "120\n");
+}
+
+#[test]
+fn nested_functions_see_the_variables_made_before_them() {
+    assert_eq!(output("fn outer() {\n  base = 7\n  fn add_base(x) { x + base }\n  return add_base(1)\n}\nprint(outer())"), "8\n");
+}
+
+#[test]
+fn the_branch_taken_decides_which_function_is_defined() {
+    assert_eq!(output("if 1 > 2 {\n  fn greet() { \"yes\" }\n} else {\n  fn greet() { \"no\" }\n}\nprint(greet())"), "no\n");
+}
+
+#[test]
+fn functions_can_reuse_the_names_of_each_others_helpers() {
+    let source = "fn helper() { \"top\" }\nfn a() {\n  fn helper() { \"a\" }\n  return helper()\n}\nfn b() {\n  fn helper(x) { x }\n  return helper(\"b\")\n}\nprint(a(), b(), helper())";
+    assert_eq!(output(source), "a\nb\ntop\n");
+}
+
+#[test]
+fn module_helpers_inside_functions_stay_local() {
+    let directory = scratch();
+    write(&directory, "lib.rash", "fn helper() { \"top\" }\nfn run() {\n  fn helper() { \"inner\" }\n  return helper()\n}\nfn other() { helper() }");
+    write(&directory, "main.rash", "import \"./lib.rash\" as lib\nprint(lib.run(), lib.other())");
+    let output = run_in(&directory, "main.rash", &[]);
+    assert_eq!(output.stdout, "inner\ntop\n", "{}", output.stderr);
+}
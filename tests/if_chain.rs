@@ -0,0 +1,25 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn only_the_first_matching_branch_runs() {
+    let source = "x = 5\nif x > 10 {\nprint(\"big\")\n} else if x > 3 {\nprint(\"medium\")\n} else if x > 1 {\nprint(\"small\")\n} else {\nprint(\"tiny\")\n}";
+    assert_eq!(output(source), "medium\n");
+}
+
+#[test]
+fn branches_can_be_split_by_blank_lines_and_comments() {
+    assert_eq!(output("if false { print(1) }\n\n// comment\nelse { print(2) }"), "2\n");
+}
+
+#[test]
+fn an_if_chain_is_an_expression() {
+    assert_eq!(output("x = 2\nsize = if x > 3 { \"big\" } else { \"small\" }\nprint(size)"), "small\n");
+    assert_eq!(output("none = if false { 1 }\nprint(none)"), "nil\n");
+}
+
+#[test]
+fn else_without_an_if_is_an_error() {
+    assert_eq!(error("else { print(1) }"), "SYNTAX ERROR: else has to follow an if or else if block");
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{run_in, scratch, write, Output};
+
+const DEPLOY: &str = "import \"./util.rash\"\nprint(\"loading deploy\")\ndeployed = 0\nexport fn run(target) {\n  global deployed\n  deployed += 1\n  return f\"deploying ${target} \" + tag()\n}\nfn hidden() { 1 }";
+
+fn project(main: &str) -> Output {
+    let directory = scratch();
+    write(&directory, "lib/deploy.rash", DEPLOY);
+    write(&directory, "lib/util.rash", "fn tag() { \"v1\" }");
+    write(&directory, "main.rash", main);
+    return run_in(&directory, "main.rash", &[]);
+}
+
+fn failure(output: &Output) -> &str {
+    assert_eq!(output.code, 1, "the script didn't fail, it printed {}", output.stdout);
+    return output.stderr.trim_end();
+}
+
+#[test]
+fn relative_imports_load_once_under_their_alias() {
+    let output = project("import \"./lib/deploy.rash\" as deploy\nimport \"./lib/deploy.rash\" as again\nprint(deploy.run(\"web\"), again.run(\"db\"), deploy.deployed)");
+    assert_eq!(output.stdout, "loading deploy\ndeploying web v1\ndeploying db v1\n2\n", "{}", output.stderr);
+}
+
+#[test]
+fn selective_and_plain_imports() {
+    let output = project("from math import pow\nimport crypto\nprint(pow(2, 8), crypto.b64(\"rash\") == b64(\"rash\"))");
+    assert_eq!(output.stdout, "256\ntrue\n", "{}", output.stderr);
+}
+
+#[test]
+fn only_exported_functions_can_be_called() {
+    assert_eq!(failure(&project("import \"./lib/deploy.rash\" as d\nd.hidden()")), "FUNCTION NOT FOUND: d.hidden wasn't found");
+    assert_eq!(failure(&project("import \"./lib/deploy.rash\" as d\nrun(\"x\")")), "FUNCTION NOT FOUND: run wasn't found");
+    assert_eq!(failure(&project("from math import nothing")), "IMPORT ERROR: math doesn't export nothing");
+}
+
+#[test]
+fn imports_can_not_replace_the_scripts_own_functions() {
+    assert_eq!(failure(&project("import math\nfn pow(a, b) { 1 }")), "IMPORT ERROR: pow from math clashes with the pow defined here, import it with `as` or rename one of them");
+}
+
+#[test]
+fn cycles_are_reported() {
+    let directory = scratch();
+    write(&directory, "a.rash", "import \"./b.rash\"\nfn fa() { 1 }");
+    write(&directory, "b.rash", "import \"./a.rash\"\nfn fb() { 1 }");
+    write(&directory, "main.rash", "import \"./a.rash\"");
+    let output = run_in(&directory, "main.rash", &[]);
+    let a = directory.join("a.rash").display().to_string();
+    let b = directory.join("b.rash").display().to_string();
+    assert_eq!(failure(&output), format!("IMPORT CYCLE: {} -> {} -> {}", a, b, a));
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn while_checks_its_condition_and_continue_skips_ahead() {
+    let source = "i = 0\nwhile i < 5 {\ni += 1\nif i == 2 { continue }\nif i == 4 { break }\nprint(i)\n}";
+    assert_eq!(output(source), "1\n3\n");
+}
+
+#[test]
+fn loop_runs_until_break() {
+    assert_eq!(output("n = 0\nloop {\nn += 1\nif n == 3 { break }\n}\nprint(n)"), "3\n");
+}
+
+#[test]
+fn labels_leave_an_outer_loop() {
+    let source = "outer: for x in [1, 2, 3] {\nfor y in [1, 2, 3] {\n\
+                  if y == 2 { continue outer }\nif x == 3 { break outer }\nprint(x * 10 + y)\n}\n}";
+    assert_eq!(output(source), "11\n21\n");
+}
+
+#[test]
+fn break_outside_of_a_loop_is_an_error() {
+    assert_eq!(error("break"), "LOOP ERROR: break and continue can only be used inside of a loop");
+    assert_eq!(error("fn f() { break }\nf()"), "LOOP ERROR: break and continue can only be used inside of a loop");
+    assert_eq!(error("for x in [1] {\nbreak missing\n}"), "LOOP ERROR: break and continue can only be used inside of a loop");
+}
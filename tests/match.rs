@@ -0,0 +1,36 @@
+mod common;
+
+use common::{error, output, run};
+
+const DESCRIBE: &str = "fn describe(x) {\nreturn match x {\n\
+                        0 => \"zero\",\n\
+                        1 | 2 => \"small\",\n\
+                        3..10 => \"medium\",\n\
+                        [a, _] => f\"pair starting with ${a}\",\n\
+                        \"hi\" => \"greeting\",\n\
+                        other => f\"other ${other}\",\n\
+                        }\n}\n";
+
+#[test]
+fn the_first_matching_arm_wins() {
+    let source = format!("{}print(describe(0), describe(2), describe(5), describe(10), describe(\"hi\"))", DESCRIBE);
+    assert_eq!(output(&source), "zero\nsmall\nmedium\nother 10\ngreeting\n");
+}
+
+#[test]
+fn array_patterns_bind_their_elements() {
+    let source = format!("{}print(describe([7, 8]), describe([7, 8, 9]) == describe([]))", DESCRIBE);
+    assert_eq!(output(&source), "pair starting with 7\ntrue\n");
+}
+
+#[test]
+fn no_matching_arm_only_warns() {
+    let warned = run("match 5 {\n0 => print(1),\n}\nprint(2)");
+    assert_eq!((warned.stdout.as_str(), warned.stderr.as_str(), warned.code), ("2\n", "MATCH WARNING: no arm matched 5\n", 0));
+}
+
+#[test]
+fn range_patterns_need_integer_bounds() {
+    assert_eq!(error("match 5 {\n0..(if false { 1 }) => print(1),\n}"), "MATCH ERROR: Range patterns need integer bounds, not int and nil");
+    assert_eq!(error("match 5 {\n0..\"a\" => print(1),\n}"), "MATCH ERROR: Range patterns need integer bounds, not int and string");
+}
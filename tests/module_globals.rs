@@ -0,0 +1,31 @@
+mod common;
+
+use common::{output, run_in, scratch, write, Output};
+
+const COUNTER: &str = "print(\"counter init\")\ncount = 0\nNAME = \"counter\"\n\nfn bump() {\n  global count\n  count += 1\n  return count\n}\n\nfn label() {\n  return f\"${NAME}: ${count}\"\n}";
+
+fn project(main: &str) -> Output {
+    let directory = scratch();
+    write(&directory, "counter.rash", COUNTER);
+    write(&directory, "other.rash", "import \"./counter.rash\" as c\nfn poke() { return c.bump() }");
+    write(&directory, "main.rash", main);
+    return run_in(&directory, "main.rash", &[]);
+}
+
+#[test]
+fn std_modules_have_constants() {
+    assert_eq!(output("import math as m\nimport math\nprint(m.PI * 2, math.PI)"), "6.283185307179586\n3.141592653589793\n");
+}
+
+#[test]
+fn module_code_runs_once_and_keeps_its_globals() {
+    let output = project("import \"./counter.rash\" as c\nimport \"./other.rash\"\nimport \"./counter.rash\"\ncount = 100\nprint(c.bump(), c.bump(), poke())\nprint(c.count, counter.count, c.NAME, count)\nprint(c.label())");
+    assert_eq!(output.stdout, "counter init\n1\n2\n3\n3\n3\ncounter\n100\ncounter: 3\n", "{}", output.stderr);
+}
+
+#[test]
+fn missing_module_variables_are_reported() {
+    let output = project("import \"./counter.rash\" as c\nprint(c.missing)");
+    assert_eq!(output.code, 1);
+    assert_eq!(output.stderr.trim_end(), "VARIABLE NOT FOUND: c.missing wasn't found");
+}
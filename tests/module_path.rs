@@ -0,0 +1,51 @@
+mod common;
+
+use common::{run_in, scratch, write};
+
+#[test]
+fn modules_next_to_the_script_come_first() {
+    let directory = scratch();
+    write(&directory, "helpers.rash", "fn where() { \"next to it\" }");
+    write(&directory, "rash_modules/helpers.rash", "fn where() { \"rash_modules\" }");
+    write(&directory, "main.rash", "import helpers\nprint(where())");
+    let output = run_in(&directory, "main.rash", &[]);
+    assert_eq!(output.stdout, "next to it\n", "{}", output.stderr);
+}
+
+#[test]
+fn project_modules_are_found_from_nested_files() {
+    let directory = scratch();
+    write(&directory, "rash_modules/helpers.rash", "fn where() { \"rash_modules\" }");
+    write(&directory, "lib/tool.rash", "import helpers\nfn run() { where() }");
+    write(&directory, "main.rash", "import \"./lib/tool.rash\" as tool\nprint(tool.run())");
+    let output = run_in(&directory, "main.rash", &[]);
+    assert_eq!(output.stdout, "rash_modules\n", "{}", output.stderr);
+}
+
+#[test]
+fn rash_path_is_searched_in_order() {
+    let directory = scratch();
+    write(&directory, "first/helpers.rash", "fn where() { \"first\" }");
+    write(&directory, "second/helpers.rash", "fn where() { \"second\" }");
+    write(&directory, "second/other.rash", "fn other() { \"other\" }");
+    write(&directory, "main.rash", "import helpers\nimport other\nprint(where(), other())");
+    let path = format!("{}:{}", directory.join("first").display(), directory.join("second").display());
+    let output = run_in(&directory, "main.rash", &[("RASH_PATH", path.as_str())]);
+    assert_eq!(output.stdout, "first\nother\n", "{}", output.stderr);
+}
+
+#[test]
+fn missing_modules_list_every_place_searched() {
+    let directory = scratch();
+    write(&directory, "main.rash", "import nothing");
+    let output = run_in(&directory, "main.rash", &[("RASH_PATH", directory.join("extra").display().to_string().as_str())]);
+    let searched = [
+        directory.join("nothing.rash"),
+        directory.join("rash_modules/nothing.rash"),
+        directory.join("extra/nothing.rash"),
+    ];
+    let searched: Vec<String> = searched.iter().map(|path| path.display().to_string()).collect();
+    let home = directory.join(".rash/std/nothing.rash");
+    assert_eq!(output.code, 1);
+    assert_eq!(output.stderr.trim_end(), format!("IMPORT NOT FOUND: Import nothing wasn't found, looked in {}, the built in std, {}", searched.join(", "), home.display()));
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn nil_compares_only_equal_to_itself() {
+    assert_eq!(output("x = nil\nprint(x == nil, 0 == nil, nil != 0, \"\" == nil)"), "true\nfalse\ntrue\nfalse\n");
+}
+
+#[test]
+fn functions_without_a_value_give_nil() {
+    assert_eq!(output("fn nothing() { return }\nprint(nothing() == nil, is_nil(nothing()), is_nil(0), type(nil), nil)"), "true\ntrue\nfalse\nnil\nnil\n");
+}
+
+#[test]
+fn coalescing_only_replaces_nil() {
+    assert_eq!(output("x = nil\nprint(x ?? \"default\", 0 ?? 5, nil ?? nil ?? 3, 1 ?? 10 / 0)"), "default\n0\n3\n1\n");
+}
+
+#[test]
+fn optional_index_gives_nil_when_missing() {
+    assert_eq!(output("arr = [1, 2]\nx = nil\nprint(arr?[1], arr?[5] ?? \"none\", x?[0] ?? \"empty\")"), "2\nnone\nempty\n");
+}
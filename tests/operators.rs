@@ -0,0 +1,31 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn integer_operators() {
+    assert_eq!(output("print(7 % 3, -7 % 3, 2 ** 10)"), "1\n-1\n1024\n");
+    assert_eq!(output("print(6 & 3, 6 | 3, 6 ^ 3, ~5, 1 << 4, -16 >> 2)"), "2\n7\n5\n-6\n16\n-4\n");
+}
+
+#[test]
+fn floor_divide_rounds_down() {
+    assert_eq!(output("print(7 ~/ 2, -7 ~/ 2, 1 + 7 ~/ 2)"), "3\n-4\n4\n");
+}
+
+#[test]
+fn compound_assignment() {
+    assert_eq!(output("x = 5\nx += 2\nx -= 1\nx *= 3\nx %= 7\nprint(x)"), "4\n");
+}
+
+#[test]
+fn increment_and_decrement_store_into_the_variable() {
+    assert_eq!(output("y = 3\n++y\n--y\n--y\nprint(y)"), "2\n");
+}
+
+#[test]
+fn increment_reports_bad_operands() {
+    assert_eq!(error("s = \"a\"\n++s"), "TYPE ERROR: Plus can't be used on String and Int");
+    assert_eq!(error("x = 2147483647\n++x"), "MATH ERROR: Plus on 2147483647 and 1 is out of range");
+    assert_eq!(error("x = -2147483648\n--x"), "MATH ERROR: Minus on -2147483648 and 1 is out of range");
+}
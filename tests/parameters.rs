@@ -0,0 +1,35 @@
+mod common;
+
+use common::{error, output, run_in, scratch, write};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn defaults_and_named_arguments() {
+    let source = "fn greet(name, greeting = \"hello\") { print(f\"${greeting} ${name}\") }\ngreet(\"bob\")\ngreet(\"bob\", greeting = \"hi\")\ngreet(name = \"ann\")";
+    assert_eq!(output(source), "hello bob\nhi bob\nhello ann\n");
+}
+
+#[test]
+fn rest_collects_the_extras() {
+    assert_eq!(output("fn log(level, ...msgs) { print(level, msgs) }\nlog(1)\nlog(1, \"a\", \"b\")"), "1\n[ ]\n1\n[ a; b; ]\n");
+}
+
+#[test]
+fn wrong_arguments_are_reported() {
+    assert_eq!(error("fn f(a) { a }\nf(1, 2)"), "INVALID ARGUMENTS: f takes at most 1 arguments but was given 2");
+    assert_eq!(error("fn f(a) { a }\nf()"), "INVALID ARGUMENTS: f is missing a value for a");
+    assert_eq!(error("fn f(a) { a }\nf(b = 1)"), "INVALID ARGUMENTS: f has no parameter called b");
+}
+
+#[test]
+fn curl_keeps_the_method_first() {
+    let directory = scratch();
+    // A stand-in curl that prints the arguments it was given
+    write(&directory, "bin/curl", "#!/bin/sh\necho \"$@\"\n");
+    fs::set_permissions(directory.join("bin/curl"), fs::Permissions::from_mode(0o755)).unwrap();
+    write(&directory, "main.rash", "import cmd\ncmd.curl(\"POST\", \"x.io\")\ncmd.get(\"y.io\")");
+    let path = format!("{}:{}", directory.join("bin").display(), std::env::var("PATH").unwrap_or_default());
+    let output = run_in(&directory, "main.rash", &[("PATH", path.as_str())]);
+    assert_eq!(output.stdout, "-X POST x.io-X GET y.io");
+}
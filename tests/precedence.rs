@@ -0,0 +1,18 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn arithmetic_follows_the_usual_precedence() {
+    assert_eq!(output("print(1 + 2 * 3, (1 + 2) * 3, 10 - 4 - 3, 2 ** 3 ** 2, -2 ** 2)"), "7\n9\n3\n512\n-4\n");
+}
+
+#[test]
+fn comparisons_and_logic_bind_looser_than_arithmetic() {
+    assert_eq!(output("print(1 + 2 == 3 && 4 < 5, !false || false, -(3 - 5))"), "true\ntrue\n2\n");
+}
+
+#[test]
+fn calls_and_indexes_bind_tightest() {
+    assert_eq!(output("fn double(x) { x * 2 }\narr = [1, 2, 3]\nprint(double(2) + arr[1] * 2, len(arr) - 1)"), "8\n2\n");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{output, run_in, scratch, write};
+
+const DEEP: &str = "fn deep(n) {\n  if n == 0 { return 0 }\n  x = deep(n - 1)\n  return x + 1\n}\n";
+
+#[test]
+fn tail_calls_do_not_nest() {
+    assert_eq!(output("fn count_down(n) {\n  if n == 0 { return \"liftoff\" }\n  return count_down(n - 1)\n}\nprint(count_down(100000))"), "liftoff\n");
+}
+
+#[test]
+fn going_too_deep_can_be_caught() {
+    let source = format!("{}r = try {{ deep(5000) }} catch e {{ print(e); -1 }}\nprint(r)", DEEP);
+    assert_eq!(output(&source), "RECURSION ERROR: deep went deeper than 1000 nested calls, RASH_MAX_DEPTH can raise the limit\n-1\n");
+}
+
+#[test]
+fn tail_calls_inside_of_try_are_still_caught() {
+    let source = "fn fail() { 1 / 0 }\nfn safe() {\n  try { return fail() } catch e { return \"caught\" }\n}\nfn fine() { 5 }\nfn checked() {\n  try { return fine() } catch e { return \"no\" }\n}\nprint(safe(), checked())";
+    assert_eq!(output(source), "caught\n5\n");
+}
+
+#[test]
+fn max_depth_can_be_raised_up_to_the_limit() {
+    let directory = scratch();
+    write(&directory, "main.rash", &format!("{}print(deep(9990))", DEEP));
+    let output = run_in(&directory, "main.rash", &[("RASH_MAX_DEPTH", "10000")]);
+    assert_eq!(output.stdout, "9990\n", "{}", output.stderr);
+    let output = run_in(&directory, "main.rash", &[("RASH_MAX_DEPTH", "10001")]);
+    assert_eq!(output.code, 1);
+    assert_eq!(output.stderr.trim_end(), "RECURSION ERROR: RASH_MAX_DEPTH has to be a whole number from 1 to 10000, not 10001");
+}
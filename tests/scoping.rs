@@ -0,0 +1,31 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn function_variables_do_not_leak_out() {
+    assert_eq!(output("i = 100\nfn loop_it() {\n  for i in 0..3 { }\n  i = 5\n  return i\n}\nprint(loop_it(), i)"), "5\n100\n");
+    assert_eq!(output("x = 1\nfn h() { x = 9; x }\nprint(h(), x)"), "9\n1\n");
+}
+
+#[test]
+fn functions_do_not_see_their_callers_variables() {
+    assert_eq!(error("fn reader() { secret }\nfn caller() {\n  secret = 1\n  return reader()\n}\nprint(caller())"), "VARIABLE NOT FOUND: secret wasn't found");
+}
+
+#[test]
+fn functions_read_globals() {
+    assert_eq!(output("x = 1\nfn g() { x }\nprint(g())"), "1\n");
+}
+
+#[test]
+fn global_writes_reach_the_file() {
+    assert_eq!(output("runs = 0\nfn track() {\n  global runs\n  runs += 1\n}\ntrack()\ntrack()\nprint(runs)"), "2\n");
+    assert_eq!(output("fn f() {\n  global made\n  made = 1\n}\nf()\nprint(made)"), "1\n");
+}
+
+#[test]
+fn let_shadows_for_the_block() {
+    assert_eq!(output("x = 1\nif true {\n  let x = \"inner\"\n  print(x)\n}\nprint(x)"), "inner\n1\n");
+    assert_eq!(error("if true { y = 3 }\nprint(y)"), "VARIABLE NOT FOUND: y wasn't found");
+}
@@ -0,0 +1,20 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn calls_start_from_the_same_body_every_time() {
+    assert_eq!(output("fn fresh() {\n  items = [1]\n  items = push(items, 2)\n  return items\n}\nprint(fresh(), fresh())"), "[ 1; 2; ]\n[ 1; 2; ]\n");
+    assert_eq!(output("fn greet(name = \"x\") { name }\nprint(greet(), greet(\"y\"), greet())"), "x\ny\nx\n");
+}
+
+#[test]
+fn copies_of_an_array_change_on_their_own() {
+    assert_eq!(output("a = [1, 2]\nb = a\nb = push(b, 3)\nprint(a, b)"), "[ 1; 2; ]\n[ 1; 2; 3; ]\n");
+}
+
+#[test]
+fn binary_search_examples_still_work() {
+    assert_eq!(output(include_str!("../examples/binary_search.rash")), "Target 101 not found\n-1\n");
+    assert_eq!(output(include_str!("../examples/binary_search_benchmark.rash")), "1000\n");
+}
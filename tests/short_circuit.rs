@@ -0,0 +1,24 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn and_skips_the_right_side_once_false() {
+    assert_eq!(output("x = 0\nprint(x != 0 && 10 / x > 1)"), "false\n");
+}
+
+#[test]
+fn or_skips_the_right_side_once_true() {
+    assert_eq!(output("x = 0\nprint(x == 0 || 10 / x > 1)"), "true\n");
+}
+
+#[test]
+fn ternary_only_runs_the_branch_taken() {
+    assert_eq!(output("x = 0\nprint(x == 0 ? \"none\" : 10 / x, x != 0 ? 10 / x : -1)"), "none\n-1\n");
+}
+
+#[test]
+fn ternary_inside_an_index_is_not_a_slice() {
+    assert_eq!(output("arr = [10, 20, 30]\nc = true\nprint(arr[c ? 1 : 2], arr[c ? 1 : 0 :])"), "20\n[ 20; 30; ]\n");
+    assert_eq!(output("s = \"hello\"\nc = false\nprint(s[c ? 0 : 1], s[1:c ? 3 : 4])"), "e\nell\n");
+}
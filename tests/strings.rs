@@ -0,0 +1,37 @@
+mod common;
+
+use common::{error, output};
+
+#[test]
+fn strings_index_from_either_end() {
+    assert_eq!(output("s = \"hello\"\nprint(s[0], s[-1])"), "h\no\n");
+}
+
+#[test]
+fn indexes_out_of_range_are_errors() {
+    assert_eq!(error("s = \"abc\"\nprint(s[5])"), "INDEX ERROR: Index 5 is out of range for length 3");
+}
+
+#[test]
+fn strings_slice_with_steps() {
+    assert_eq!(output("s = \"hello world\"\nprint(s[0:5], s[6:], s[::-1], s[::2])"), "hello\nworld\ndlrow olleh\nhlowrd\n");
+}
+
+#[test]
+fn a_slice_inside_of_an_index_stays_inside() {
+    assert_eq!(output("s = \"hello world\"\narr = [10, 20, 30]\nprint(arr[len(s[9:])])"), "30\n");
+    assert_eq!(output("s = \"hello world\"\nprint(s[len(s[8:])])"), "l\n");
+}
+
+#[test]
+fn string_builtins() {
+    let source = "print(split(\"a,b\", \",\"), join([\"a\", \"b\"], \"-\"), trim(\"  hi  \"), replace(\"aaa\", \"a\", \"b\"))\n\
+                  print(contains(\"abc\", \"b\"), starts_with(\"abc\", \"ab\"), ends_with(\"abc\", \"x\"), upper(\"abc\"), lower(\"ABC\"), len(\"abc\"))";
+    assert_eq!(output(source), "[ a; b; ]\na-b\nhi\nbbb\ntrue\ntrue\nfalse\nABC\nabc\n3\n");
+}
+
+#[test]
+fn string_builtins_report_bad_arguments() {
+    assert_eq!(error("print(upper(5))"), "TYPE ERROR: upper needs a string, not int");
+    assert_eq!(error("print(len(5))"), "TYPE ERROR: len needs a string or an array, not int");
+}
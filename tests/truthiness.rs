@@ -0,0 +1,29 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn bool_converts_by_truthiness() {
+    assert_eq!(output("print(bool(0), bool(3), bool(0.0), bool(\"\"), bool(\"a\"), bool([]), bool([0]), bool(nil))"), "false\ntrue\nfalse\nfalse\ntrue\nfalse\ntrue\nfalse\n");
+}
+
+#[test]
+fn conditions_accept_any_value() {
+    assert_eq!(output("if 0 { print(\"no\") } else { print(\"zero\") }\nif \"x\" { print(\"text\") }"), "zero\ntext\n");
+    assert_eq!(output("arr = []\nif len(arr) { print(\"no\") } else { print(\"empty\") }"), "empty\n");
+}
+
+#[test]
+fn loops_stop_on_falsy_values() {
+    assert_eq!(output("arr = []\nwhile arr { print(\"never\") }\nn = 3\nwhile n { n -= 1 }\nprint(n)"), "0\n");
+}
+
+#[test]
+fn commands_are_true_when_they_succeed() {
+    assert_eq!(output("if c\"false\" { print(\"no\") } else { print(\"failed\") }\nif c\"true\" { print(\"ok\") }"), "failed\nok\n");
+}
+
+#[test]
+fn logic_and_ternaries_use_truthiness() {
+    assert_eq!(output("print(!0, !\"\", 0 || \"b\", [] && 1, 0 ? \"y\" : \"n\")"), "true\ntrue\ntrue\nfalse\nn\n");
+}